@@ -0,0 +1,90 @@
+// time `status` against `status --root-only` on a clean tree with many files and submodules,
+// the root of the latter is checked by the quick diff instead of its full status
+// then `status --name-only` against `status --stat` once a tenth of the files are changed,
+// the former takes the paths from the status entries and never reads a blob
+// run with `cargo bench --bench status`, the sizes can be changed by
+// GIT_SUB_BENCH_FILES, GIT_SUB_BENCH_SUBMODULES and GIT_SUB_BENCH_RUNS
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::*;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+fn env_or(name: &str, default: usize) -> usize {
+  std::env::var(name)
+    .ok()
+    .and_then(|v| v.parse().ok())
+    .unwrap_or(default)
+}
+
+// the mean time of a run of git-sub with the arguments
+fn time_runs(dir: &Path, args: &[&str], runs: usize) -> Duration {
+  // warm up the file system cache
+  git_sub_ok(dir, args);
+  let start = Instant::now();
+  for _ in 0..runs {
+    git_sub_ok(dir, args);
+  }
+  start.elapsed() / runs as u32
+}
+
+fn main() {
+  let files = env_or("GIT_SUB_BENCH_FILES", 20000);
+  let submodules = env_or("GIT_SUB_BENCH_SUBMODULES", 20);
+  let runs = env_or("GIT_SUB_BENCH_RUNS", 10);
+
+  let dir = temp_dir("bench-status");
+  let root = dir.join("root");
+  for i in 0..files {
+    write_file(
+      &root,
+      &format!("d{}/f{}.txt", i % 100, i),
+      &format!("{}\n", i),
+    );
+  }
+  git(&root, &["init", "-q"]);
+  git(&root, &["add", "."]);
+  git(&root, &["commit", "-q", "-m", "files"]);
+  for i in 0..submodules {
+    let sub = new_repo(&dir.join(format!("s{}", i)), "s.txt", "s\n");
+    for j in 0..files / submodules.max(1) / 10 {
+      write_file(&sub, &format!("f{}.txt", j), &format!("{}\n", j));
+    }
+    git(&sub, &["add", "."]);
+    git(&sub, &["commit", "-q", "-m", "files"]);
+    let url = sub.to_str().expect("Non utf-8 path");
+    git(
+      &root,
+      &["submodule", "add", "-q", url, &format!("subs/s{}", i)],
+    );
+  }
+  if submodules > 0 {
+    git(&root, &["commit", "-q", "-m", "submodules"]);
+  }
+
+  println!(
+    "clean tree of {} files and {} submodules, mean of {} runs",
+    files, submodules, runs
+  );
+  for args in [&["status"][..], &["status", "--root-only"]] {
+    println!("{:>24}: {:?}", args.join(" "), time_runs(&root, args, runs));
+  }
+
+  for i in (0..files).step_by(10) {
+    write_file(
+      &root,
+      &format!("d{}/f{}.txt", i % 100, i),
+      &format!("{}\nchanged\n", i),
+    );
+  }
+  println!("{} of the files changed", files.div_ceil(10));
+  for args in [
+    &["status"][..],
+    &["status", "--stat"],
+    &["status", "--name-only"],
+  ] {
+    println!("{:>24}: {:?}", args.join(" "), time_runs(&root, args, runs));
+  }
+  let _ = std::fs::remove_dir_all(&dir);
+}
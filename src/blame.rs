@@ -0,0 +1,188 @@
+use super::*;
+use clap::*;
+use std::collections::HashSet;
+
+pub struct BlameArgs {
+  path: PathBuf,
+  porcelain: bool,
+}
+
+impl BlameArgs {
+  pub fn build_arg() -> Command {
+    Command::new("blame")
+      .about("Show the commit of each line of a file, in the submodule the file belongs to")
+      .arg(
+        Arg::new("porcelain")
+          .long("porcelain")
+          .action(ArgAction::SetTrue)
+          .help("Print git's blame porcelain format for tools, with full ids and no color"),
+      )
+      .arg(
+        Arg::new("file")
+          .required(true)
+          .help("The file to blame, relative to the current directory"),
+      )
+  }
+}
+
+impl From<&clap::ArgMatches> for BlameArgs {
+  fn from(matches: &clap::ArgMatches) -> BlameArgs {
+    BlameArgs {
+      path: PathBuf::from(matches.get_one::<String>("file").unwrap()),
+      porcelain: matches.get_flag("porcelain"),
+    }
+  }
+}
+
+// the submodule the path belongs to and the path relative to it
+// the path is relative to the repo, the deepest submodule containing it wins
+fn find_repo_of(repo: Repository, path: &Path) -> Result<(Repository, PathBuf), GitSubError> {
+  let found = match repo
+    .submodules()?
+    .iter()
+    .find(|sub| path.starts_with(sub.path()))
+  {
+    // the file can only be blamed in its own submodule, so it can't be skipped
+    Some(sub) => match sub.open() {
+      Ok(sub_repo) => Some((
+        sub_repo,
+        path.strip_prefix(sub.path()).unwrap().to_path_buf(),
+      )),
+      Err(e) => {
+        return Err(GitSubError::SubmoduleOpen(
+          submodule_display_path(&repo, sub),
+          e,
+        ))
+      }
+    },
+    None => None,
+  };
+  match found {
+    Some((sub_repo, rest)) => find_repo_of(sub_repo, &rest),
+    None => Ok((repo, path.to_path_buf())),
+  }
+}
+
+// the time zone of the signature like "+0800"
+fn format_tz(time: Time) -> String {
+  let offset = time.offset_minutes();
+  let sign = if offset < 0 { '-' } else { '+' };
+  format!("{}{:02}{:02}", sign, offset.abs() / 60, offset.abs() % 60)
+}
+
+// print the lines like `git blame --porcelain`, the details of a commit are only given
+// the first time it shows up
+fn print_porcelain(
+  repo: &Repository,
+  blame: &Blame,
+  lines: &[&str],
+  path: &str,
+) -> Result<(), GitSubError> {
+  let mut shown = HashSet::new();
+  for hunk in blame.iter() {
+    let id = hunk.final_commit_id();
+    for i in 0..hunk.lines_in_hunk() {
+      let final_line = hunk.final_start_line() + i;
+      let orig_line = hunk.orig_start_line() + i;
+      if i == 0 {
+        println!(
+          "{} {} {} {}",
+          id,
+          orig_line,
+          final_line,
+          hunk.lines_in_hunk()
+        );
+        if shown.insert(id) {
+          let commit = repo.find_commit(id)?;
+          for (name, sig) in [
+            ("author", commit.author()),
+            ("committer", commit.committer()),
+          ] {
+            println!("{} {}", name, sig.name().unwrap_or_default());
+            println!("{}-mail <{}>", name, sig.email().unwrap_or_default());
+            println!("{}-time {}", name, sig.when().seconds());
+            println!("{}-tz {}", name, format_tz(sig.when()));
+          }
+          println!("summary {}", commit.summary().unwrap_or_default());
+          // the parent the lines would be blamed on next, if the file is there
+          let orig_path = hunk.path().map(Path::to_path_buf).unwrap_or_default();
+          let previous = commit.parents().find(|p| {
+            p.tree()
+              .map(|t| t.get_path(&orig_path).is_ok())
+              .unwrap_or(false)
+          });
+          if hunk.is_boundary() {
+            println!("boundary");
+          } else if let Some(p) = previous {
+            println!("previous {} {}", p.id(), display_path(&orig_path));
+          }
+          println!("filename {}", path);
+        }
+      } else {
+        println!("{} {} {}", id, orig_line, final_line);
+      }
+      println!("\t{}", lines.get(final_line - 1).unwrap_or(&""));
+    }
+  }
+  Ok(())
+}
+
+// print the lines like `git blame`, with the short id, the author and the date
+fn print_blame(blame: &Blame, lines: &[&str]) {
+  let author_width = blame
+    .iter()
+    .map(|h| text_width(h.final_signature().name().unwrap_or_default()))
+    .max()
+    .unwrap_or(0);
+  let line_width = lines.len().to_string().len();
+  let now = chrono::Local::now();
+  for hunk in blame.iter() {
+    let sig = hunk.final_signature();
+    let id = hunk.final_commit_id().to_string();
+    for i in 0..hunk.lines_in_hunk() {
+      let line_no = hunk.final_start_line() + i;
+      println!(
+        "{} ({} {} {:>width$}) {}",
+        id[..8].paint(color_for("log.hash")),
+        pad_to_width(sig.name().unwrap_or_default(), author_width).bright_blue(),
+        format_time(sig.when(), DateMode::Absolute, now).paint(color_for("log.date")),
+        line_no,
+        lines.get(line_no - 1).unwrap_or(&""),
+        width = line_width
+      );
+    }
+  }
+}
+
+// blame the committed file in the submodule it belongs to, the working tree changes are left out
+pub fn blame(repo: Repository, args: BlameArgs) -> Result<(), GitSubError> {
+  let path = std::env::current_dir()?.join(&args.path);
+  let path = path
+    .canonicalize()
+    .map_err(|_| GitSubError::NoSuchPath(display_path(&args.path)))?;
+  let root = repo
+    .workdir()
+    .and_then(|p| p.canonicalize().ok())
+    .ok_or(GitSubError::BareRepo)?;
+  let rel_path = path
+    .strip_prefix(&root)
+    .map_err(|_| GitSubError::OutsideRepo(display_path(&path)))?
+    .to_path_buf();
+  let (repo, file) = find_repo_of(repo, &rel_path)?;
+
+  let blame = repo.blame_file(&file, None)?;
+  let blob = repo
+    .head()
+    .and_then(|h| h.peel_to_tree())
+    .and_then(|t| t.get_path(&file))
+    .and_then(|e| repo.find_blob(e.id()))?;
+  let content = String::from_utf8_lossy(blob.content());
+  let lines: Vec<&str> = content.lines().collect();
+  if args.porcelain {
+    disable_color();
+    print_porcelain(&repo, &blame, &lines, &display_path(&file))?;
+  } else {
+    print_blame(&blame, &lines);
+  }
+  Ok(())
+}
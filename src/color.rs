@@ -1,21 +1,32 @@
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 // The replacement of https://github.com/mackwic/colored to support color in cmd
+// stdout and stderr are decided separately, e.g. stdout can be piped while stderr is a terminal
 static mut DO_COLOR: bool = false;
+static mut DO_COLOR_STDERR: bool = false;
 static mut CSTDOUT: Option<StandardStream> = None;
 
-pub fn check_tty() {
+// the color of stdout is decided by, in order: CLICOLOR_FORCE or --force-color,
+// writing to the pager, then whether stdout is a terminal
+pub fn check_tty(paged: bool) {
   if let Ok(s) = std::env::var("CLICOLOR_FORCE") {
     if !(s.len() == 0 || s == "0") {
       unsafe {
         DO_COLOR = true;
+        DO_COLOR_STDERR = true;
       }
       return;
     }
   }
-  if atty::is(atty::Stream::Stdout) {
-    unsafe {
-      DO_COLOR = true;
-    }
+  // the pager shows the output on the terminal, so it's colored as if stdout was the terminal
+  unsafe {
+    DO_COLOR = paged || atty::is(atty::Stream::Stdout);
+    DO_COLOR_STDERR = atty::is(atty::Stream::Stderr);
+  }
+}
+// turn off the color of stdout, e.g. for machine readable output
+pub fn disable_color() {
+  unsafe {
+    DO_COLOR = false;
   }
 }
 fn do_color() -> bool {
@@ -23,6 +34,99 @@ fn do_color() -> bool {
     return DO_COLOR;
   }
 }
+fn do_color_stderr() -> bool {
+  unsafe { DO_COLOR_STDERR }
+}
+
+// the elements whose color can be overridden by GIT_SUB_COLORS, with their default colors
+const ELEMENTS: [(&str, &str); 6] = [
+  ("log.hash", "red"),
+  ("log.date", "green"),
+  ("status.added", "green"),
+  ("diff.add", "green"),
+  ("diff.remove", "red"),
+  ("diff.hunk", "cyan"),
+];
+static COLOR_OVERRIDES: std::sync::OnceLock<Vec<(String, ColorSpec)>> = std::sync::OnceLock::new();
+
+// the color by its name, "bright-" makes it intense
+fn parse_color(name: &str) -> Option<ColorSpec> {
+  let (bright, name) = match name.strip_prefix("bright-") {
+    Some(n) => (true, n),
+    None => (false, name),
+  };
+  let fg = match name {
+    "black" => Some(Color::Black),
+    "red" => Some(Color::Red),
+    "green" => Some(Color::Green),
+    "yellow" => Some(Color::Yellow),
+    "blue" => Some(Color::Blue),
+    "purple" | "magenta" => Some(Color::Magenta),
+    "cyan" => Some(Color::Cyan),
+    "white" => Some(Color::White),
+    "default" => None,
+    _ => return None,
+  };
+  let mut color = ColorSpec::new();
+  color.set_fg(fg).set_intense(bright);
+  Some(color)
+}
+
+// parse GIT_SUB_COLORS like "diff.add=blue:log.hash=bright-yellow" once
+// the unknown elements and colors are warned about and skipped
+fn color_overrides() -> &'static Vec<(String, ColorSpec)> {
+  COLOR_OVERRIDES.get_or_init(|| {
+    let value = std::env::var("GIT_SUB_COLORS").unwrap_or_default();
+    let mut overrides = Vec::new();
+    for item in value.split(':').filter(|s| !s.is_empty()) {
+      let (element, name) = item.split_once('=').unwrap_or((item, ""));
+      if !ELEMENTS.iter().any(|(e, _)| *e == element) {
+        eprintln!("Warning: unknown element in GIT_SUB_COLORS: {}", element);
+        continue;
+      }
+      match parse_color(name) {
+        Some(color) => overrides.push((String::from(element), color)),
+        None => eprintln!("Warning: unknown color in GIT_SUB_COLORS: {}", item),
+      }
+    }
+    overrides
+  })
+}
+
+// the color of the element given by GIT_SUB_COLORS
+pub fn color_override(element: &str) -> Option<ColorSpec> {
+  color_overrides()
+    .iter()
+    .find(|(e, _)| e == element)
+    .map(|(_, c)| c.clone())
+}
+
+// the color of the element, GIT_SUB_COLORS or the default
+pub fn color_for(element: &str) -> ColorSpec {
+  color_override(element).unwrap_or_else(|| {
+    ELEMENTS
+      .iter()
+      .find(|(e, _)| *e == element)
+      .and_then(|(_, name)| parse_color(name))
+      .unwrap_or_default()
+  })
+}
+
+// print the error message to stderr, in red if stderr is a terminal
+pub fn print_error(msg: &str) {
+  use std::io::Write;
+  if do_color_stderr() {
+    let mut stderr = StandardStream::stderr(ColorChoice::Auto);
+    let mut color = ColorSpec::new();
+    color.set_fg(Some(Color::Red));
+    stderr.set_color(&color).unwrap();
+    write!(stderr, "{}", msg).unwrap();
+    stderr.reset().unwrap();
+    writeln!(stderr).unwrap();
+  } else {
+    eprintln!("{}", msg);
+  }
+}
 pub trait StrColor {
   fn red(&self) -> ColoredString;
   fn green(&self) -> ColoredString;
@@ -31,6 +135,7 @@ pub trait StrColor {
   fn bright_blue(&self) -> ColoredString;
   fn yellow(&self) -> ColoredString;
   fn default(&self) -> ColoredString;
+  fn paint(&self, color: ColorSpec) -> ColoredString;
 }
 
 impl StrColor for str {
@@ -89,6 +194,12 @@ impl StrColor for str {
       color: color,
     }
   }
+  fn paint(&self, color: ColorSpec) -> ColoredString {
+    ColoredString {
+      text: String::from(self),
+      color,
+    }
+  }
 }
 
 pub struct ColoredString {
@@ -0,0 +1,247 @@
+use super::*;
+use std::collections::HashSet;
+
+// the lines within this distance of a change are shown as context
+const CONTEXT_LINES: usize = 3;
+
+// how the changes of the merge commits are shown, like `git log --diff-merges`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DiffMerges {
+  // no changes at all for the merges
+  Off,
+  // against the first parent, like any other commit
+  FirstParent,
+  // the combined diff against all parents
+  Combined,
+}
+
+impl DiffMerges {
+  pub const VALUES: [&'static str; 3] = ["off", "first-parent", "combined"];
+
+  pub fn from(s: &str) -> DiffMerges {
+    match s {
+      "off" => DiffMerges::Off,
+      "first-parent" => DiffMerges::FirstParent,
+      "combined" => DiffMerges::Combined,
+      _ => err_exit!("Unknown diff merges mode: {}", s),
+    }
+  }
+}
+
+// how the file of the merge differs from a parent, indexed by the lines of the merge
+struct ParentDiff {
+  // the lines of the merge which are not in the parent
+  added: Vec<bool>,
+  // the lines of the parent which are lost before each line of the merge, one more for the end
+  removed: Vec<Vec<String>>,
+}
+
+fn blob_content(repo: &Repository, tree: &Tree, path: &Path) -> Option<Vec<u8>> {
+  tree
+    .get_path(path)
+    .ok()
+    .filter(|e| e.kind() == Some(ObjectType::Blob))
+    .and_then(|e| repo.find_blob(e.id()).ok())
+    .map(|b| b.content().to_vec())
+}
+
+fn parent_diff(old: &[u8], new: &[u8], new_lines: usize) -> ParentDiff {
+  let mut diff = ParentDiff {
+    added: vec![false; new_lines],
+    removed: vec![Vec::new(); new_lines + 1],
+  };
+  let mut opts = DiffOptions::new();
+  opts.context_lines(0).minimal(is_minimal_diff());
+  let patch = Patch::from_buffers(old, None, new, None, Some(&mut opts))
+    .unwrap_or_else(|e| err_exit!("Diff failed: {}", e));
+  for h in 0..patch.num_hunks() {
+    let (hunk, line_count) = patch.hunk(h).expect("Get hunk failed");
+    // a hunk without new lines is placed after the line it starts at
+    let pos = if hunk.new_lines() == 0 {
+      hunk.new_start() as usize
+    } else {
+      hunk.new_start() as usize - 1
+    };
+    for l in 0..line_count {
+      let line = patch.line_in_hunk(h, l).expect("Get line failed");
+      match line.origin() {
+        '+' => {
+          if let Some(n) = line.new_lineno() {
+            diff.added[n as usize - 1] = true;
+          }
+        }
+        '-' => diff.removed[pos].push(String::from_utf8_lossy(line.content()).into_owned()),
+        _ => {}
+      }
+    }
+  }
+  diff
+}
+
+// the files of the merge which differ from every parent with the kind of the change
+pub fn combined_changes(repo: &Repository, commit: &Commit) -> Vec<(Delta, PathBuf)> {
+  let tree = commit.tree().expect("Get tree failed");
+  let parent_trees: Vec<Tree> = commit
+    .parents()
+    .map(|p| p.tree().expect("Get tree failed"))
+    .collect();
+  let mut paths: Option<HashSet<PathBuf>> = None;
+  for parent_tree in parent_trees.iter() {
+    let diff = repo
+      .diff_tree_to_tree(Some(parent_tree), Some(&tree), None)
+      .expect("Get diff from parent failed");
+    let changed: HashSet<PathBuf> = diff
+      .deltas()
+      .filter_map(|d| d.new_file().path().or_else(|| d.old_file().path()))
+      .map(Path::to_path_buf)
+      .collect();
+    paths = Some(match paths {
+      Some(p) => p.intersection(&changed).cloned().collect(),
+      None => changed,
+    });
+  }
+  let mut paths: Vec<PathBuf> = paths.unwrap_or_default().into_iter().collect();
+  paths.sort();
+  paths
+    .into_iter()
+    .map(|p| {
+      let kind = if tree.get_path(&p).is_err() {
+        Delta::Deleted
+      } else if parent_trees.iter().all(|t| t.get_path(&p).is_err()) {
+        Delta::Added
+      } else {
+        Delta::Modified
+      };
+      (kind, p)
+    })
+    .collect()
+}
+
+fn print_line(columns: &str, content: &str) {
+  let (body, ending) = split_line_ending(content);
+  let msg = format!("{}{}", columns, body);
+  if columns.contains('-') {
+    print!("{}", msg.paint(color_for("diff.remove")));
+  } else if columns.contains('+') {
+    print!("{}", msg.paint(color_for("diff.add")));
+  } else {
+    print!("{}", msg);
+  }
+  if ending.is_empty() {
+    println!();
+  } else {
+    print!("{}", ending);
+  }
+}
+
+// print the combined diff of a file like `git diff --cc`
+// a column for each parent tells if the line is added to or removed from that parent
+// only the hunks which differ from every parent are shown
+fn print_combined_file(repo: &Repository, commit: &Commit, path: &Path, prefix: &str) {
+  let tree = commit.tree().expect("Get tree failed");
+  let parent_trees: Vec<Tree> = commit
+    .parents()
+    .map(|p| p.tree().expect("Get tree failed"))
+    .collect();
+  let path_str = path.display().to_string().replace("\\", "/");
+  println!("diff --cc {}{}", prefix, path_str);
+
+  let new = blob_content(repo, &tree, path).unwrap_or_default();
+  let olds: Vec<Vec<u8>> = parent_trees
+    .iter()
+    .map(|t| blob_content(repo, t, path).unwrap_or_default())
+    .collect();
+  if is_binary(&new) || olds.iter().any(|o| is_binary(o)) {
+    println!("Binary files differ");
+    return;
+  }
+  println!("--- a/{}{}", prefix, path_str);
+  println!("+++ b/{}{}", prefix, path_str);
+
+  let new_text = String::from_utf8_lossy(&new);
+  let lines: Vec<&str> = new_text.split_inclusive('\n').collect();
+  let diffs: Vec<ParentDiff> = olds
+    .iter()
+    .map(|o| parent_diff(o, &new, lines.len()))
+    .collect();
+
+  // a position differs from every parent if each of them lost lines there or lacks the line
+  let interesting = |pos: usize| {
+    diffs
+      .iter()
+      .all(|d| !d.removed[pos].is_empty() || (pos < lines.len() && d.added[pos]))
+  };
+  let mut hunks: Vec<(usize, usize)> = Vec::new();
+  for pos in (0..=lines.len()).filter(|p| interesting(*p)) {
+    let start = pos.saturating_sub(CONTEXT_LINES);
+    let end = (pos + CONTEXT_LINES + 1).min(lines.len());
+    match hunks.last_mut() {
+      Some(last) if last.1 >= start => last.1 = last.1.max(end),
+      _ => hunks.push((start, end)),
+    }
+  }
+
+  let marker = "@".repeat(diffs.len() + 1);
+  for (start, end) in hunks {
+    // the range of each parent, counting its lines before and within the hunk
+    let mut header = marker.clone();
+    for d in diffs.iter() {
+      let count_old = |from: usize, to: usize, with_last: bool| -> usize {
+        let last = if with_last { to + 1 } else { to };
+        (from..last).map(|p| d.removed[p].len()).sum::<usize>()
+          + (from..to).filter(|p| !d.added[*p]).count()
+      };
+      let before = count_old(0, start, false);
+      let count = count_old(start, end, true);
+      header += &format!(" -{},{}", before + 1, count);
+    }
+    header += &format!(" +{},{} {}", start + 1, end - start, marker);
+    println!("{}", header.paint(color_for("diff.hunk")));
+
+    for pos in start..=end {
+      // the same line lost from several parents is shown once
+      let mut lost: Vec<(&str, Vec<bool>)> = Vec::new();
+      for (i, d) in diffs.iter().enumerate() {
+        for line in d.removed[pos].iter() {
+          match lost.iter_mut().find(|(l, cols)| *l == line && !cols[i]) {
+            Some((_, cols)) => cols[i] = true,
+            None => {
+              let mut cols = vec![false; diffs.len()];
+              cols[i] = true;
+              lost.push((line, cols));
+            }
+          }
+        }
+      }
+      for (line, cols) in lost.iter() {
+        let columns: String = cols.iter().map(|c| if *c { '-' } else { ' ' }).collect();
+        print_line(&columns, line);
+      }
+      if let Some(line) = lines.get(pos).filter(|_| pos < end) {
+        let columns: String = diffs
+          .iter()
+          .map(|d| if d.added[pos] { '+' } else { ' ' })
+          .collect();
+        print_line(&columns, line);
+      }
+    }
+  }
+}
+
+// print the combined diff of every file of the merge which differs from all parents
+// the submodules are left out, their commits are in the log on their own
+pub fn print_combined_diff(repo: &Repository, commit: &Commit, prefix: &str) {
+  let tree = commit.tree().expect("Get tree failed");
+  for (_, path) in combined_changes(repo, commit) {
+    let is_link =
+      |t: &Tree| matches!(t.get_path(&path), Ok(e) if e.kind() == Some(ObjectType::Commit));
+    if is_link(&tree)
+      || commit
+        .parents()
+        .any(|p| is_link(&p.tree().expect("Get tree failed")))
+    {
+      continue;
+    }
+    print_combined_file(repo, commit, &path, prefix);
+  }
+}
@@ -0,0 +1,192 @@
+use super::*;
+use clap::*;
+use std::fs;
+
+pub struct CountArgs {
+  json: bool,
+}
+
+impl CountArgs {
+  pub fn build_arg() -> Command {
+    Command::new("count-objects")
+      .about("Count objects and disk usage of each submodule")
+      .arg(
+        Arg::new("json")
+          .long("json")
+          .action(ArgAction::SetTrue)
+          .help("Print the result in json"),
+      )
+  }
+}
+
+impl From<&clap::ArgMatches> for CountArgs {
+  fn from(matches: &clap::ArgMatches) -> CountArgs {
+    CountArgs {
+      json: matches.get_flag("json"),
+    }
+  }
+}
+
+struct RepoCount {
+  path: String,
+  objects: usize,
+  size: u64,
+  files: usize,
+  // a submodule which isn't checked out has nothing to count
+  initialized: bool,
+}
+
+// sum up the size of all files under the directory
+fn dir_size(path: &Path) -> u64 {
+  let entries = match fs::read_dir(path) {
+    Ok(e) => e,
+    Err(_) => return 0,
+  };
+  entries
+    .filter_map(|e| e.ok())
+    .map(|e| match e.file_type() {
+      Ok(t) if t.is_dir() => dir_size(&e.path()),
+      Ok(_) => e.metadata().map(|m| m.len()).unwrap_or(0),
+      Err(_) => 0,
+    })
+    .sum()
+}
+
+pub fn format_size(size: u64) -> String {
+  const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+  if size < 1024 {
+    return format!("{} B", size);
+  }
+  let mut value = size as f64 / 1024.0;
+  let mut unit = 0;
+  while value >= 1024.0 && unit < UNITS.len() - 1 {
+    value /= 1024.0;
+    unit += 1;
+  }
+  format!("{:.1} {}", value, UNITS[unit])
+}
+
+fn count_repo(
+  repo: &Repository,
+  work_dir: &Path,
+  counts: &mut Vec<RepoCount>,
+) -> Result<(), GitSubError> {
+  let mut objects = 0;
+  repo.odb()?.foreach(|_| {
+    objects += 1;
+    true
+  })?;
+
+  counts.push(RepoCount {
+    path: repo_display_path(repo, work_dir),
+    objects,
+    size: dir_size(&repo.path().join("objects")),
+    files: repo.index().map(|i| i.len()).unwrap_or(0),
+    initialized: true,
+  });
+
+  let repo_dir = repo
+    .workdir()
+    .and_then(|p| p.canonicalize().ok())
+    .unwrap_or_default();
+  for sub in repo.submodules()?.iter() {
+    match sub.open() {
+      Ok(sub_repo) => count_repo(&sub_repo, work_dir, counts)?,
+      Err(e) => {
+        let sub_dir = repo_dir.join(sub.path());
+        let path = relative_path(&sub_dir, work_dir);
+        if is_strict() {
+          return Err(GitSubError::SubmoduleOpen(path, e));
+        }
+        eprintln!("Warning: can't open submodule {}: {}", path, e.message());
+        counts.push(RepoCount {
+          path,
+          objects: 0,
+          size: 0,
+          files: 0,
+          initialized: false,
+        });
+      }
+    }
+  }
+  Ok(())
+}
+
+pub fn count_objects(
+  repo: Repository,
+  work_dir: &Path,
+  args: CountArgs,
+) -> Result<(), GitSubError> {
+  let mut counts = Vec::new();
+  count_repo(&repo, work_dir, &mut counts)?;
+  let total = RepoCount {
+    path: String::from("Total"),
+    objects: counts.iter().map(|c| c.objects).sum(),
+    size: counts.iter().map(|c| c.size).sum(),
+    files: counts.iter().map(|c| c.files).sum(),
+    initialized: true,
+  };
+
+  if args.json {
+    let repos: Vec<String> = counts
+      .iter()
+      .map(|c| {
+        format!(
+          "{{\"path\":{},\"initialized\":{},\"objects\":{},\"size\":{},\"files\":{}}}",
+          json::json_str(&c.path),
+          c.initialized,
+          c.objects,
+          c.size,
+          c.files
+        )
+      })
+      .collect();
+    println!(
+      "{{\"repos\":[{}],\"total\":{{\"objects\":{},\"size\":{},\"files\":{}}}}}",
+      repos.join(","),
+      total.objects,
+      total.size,
+      total.files
+    );
+    return Ok(());
+  }
+
+  let width = counts
+    .iter()
+    .map(|c| text_width(&c.path))
+    .max()
+    .unwrap_or(0)
+    .max(text_width(&total.path));
+  println!(
+    "{}  {:>10}  {:>10}  {:>10}",
+    pad_to_width("Repo", width),
+    "Objects",
+    "Size",
+    "Files"
+  );
+  for c in counts.iter() {
+    if !c.initialized {
+      println!(
+        "{}  {}",
+        pad_to_width(&c.path, width).bright_blue(),
+        "not initialized".yellow()
+      );
+      continue;
+    }
+    println!(
+      "{}  {:>10}  {:>10}  {:>10}",
+      pad_to_width(&c.path, width).bright_blue(),
+      c.objects,
+      format_size(c.size),
+      c.files
+    );
+  }
+  println!(
+    "{}  {:>10}  {:>10}  {:>10}",
+    pad_to_width(&total.path, width).green(),
+    total.objects,
+    format_size(total.size),
+    total.files
+  );
+  Ok(())
+}
@@ -0,0 +1,95 @@
+use super::*;
+use chrono::prelude::*;
+
+// how the timestamps are displayed
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DateMode {
+  Relative,
+  Absolute,
+}
+
+impl DateMode {
+  pub const VALUES: [&'static str; 2] = ["relative", "absolute"];
+
+  pub fn from(s: &str) -> DateMode {
+    match s {
+      "relative" => DateMode::Relative,
+      "absolute" => DateMode::Absolute,
+      _ => err_exit!("Unknown date mode: {}", s),
+    }
+  }
+}
+
+pub fn format_duration(dur: chrono::Duration) -> String {
+  if dur.num_days() > 30 {
+    format!("{} months ago", dur.num_days() / 30)
+  } else if dur.num_days() > 0 {
+    format!("{} days ago", dur.num_days())
+  } else if dur.num_hours() > 0 {
+    format!("{} hours ago", dur.num_hours())
+  } else if dur.num_minutes() > 0 {
+    format!("{} mins ago", dur.num_minutes())
+  } else if dur.num_seconds() > 0 {
+    format!("{} secs ago", dur.num_seconds())
+  } else {
+    String::from("just now")
+  }
+}
+
+// format the git time, `now` is used as the reference point of the relative mode
+pub fn format_time(time: git2::Time, mode: DateMode, now: DateTime<Local>) -> String {
+  let local_time = Local
+    .timestamp_opt(time.seconds(), 0)
+    .single()
+    .unwrap_or_else(|| err_exit!("Invalid timestamp: {}", time.seconds()));
+  match mode {
+    DateMode::Relative => format_duration(now - local_time),
+    DateMode::Absolute => local_time.format("%a %b %d %T %Y %z").to_string(),
+  }
+}
+
+// format the git time in ISO 8601 keeping its own timezone, for machine readable output
+pub fn format_iso(time: git2::Time) -> String {
+  FixedOffset::east_opt(time.offset_minutes() * 60)
+    .and_then(|tz| tz.timestamp_opt(time.seconds(), 0).single())
+    .map(|t| t.to_rfc3339())
+    .unwrap_or_else(|| err_exit!("Invalid timestamp: {}", time.seconds()))
+}
+
+// parse the date given to the options like --since
+// accept "2022-10-01", "2022-10-01 12:00:00", RFC 3339 and relative dates like "2 weeks ago"
+pub fn parse_date(s: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+  let s = s.trim();
+  if let Ok(d) = DateTime::parse_from_rfc3339(s) {
+    return Some(d.with_timezone(&Local));
+  }
+  if let Ok(d) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") {
+    return Local.from_local_datetime(&d).single();
+  }
+  if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+    return Local.from_local_datetime(&d.and_hms_opt(0, 0, 0)?).single();
+  }
+  let s = s.strip_suffix("ago").unwrap_or(s);
+  Some(now - parse_duration(s)?)
+}
+
+// parse the duration like "2 weeks" or "6 months", a month is 30 days and a year 365
+pub fn parse_duration(s: &str) -> Option<chrono::Duration> {
+  let mut words = s.split_whitespace();
+  let num = words.next()?.parse::<i64>().ok()?;
+  let unit = words.next()?.trim_end_matches('s');
+  if words.next().is_some() {
+    return None;
+  }
+  let dur = match unit {
+    "sec" | "second" => chrono::Duration::seconds(num),
+    "min" | "minute" => chrono::Duration::minutes(num),
+    "hour" => chrono::Duration::hours(num),
+    "day" => chrono::Duration::days(num),
+    "week" => chrono::Duration::weeks(num),
+    "month" => chrono::Duration::days(num * 30),
+    "year" => chrono::Duration::days(num * 365),
+    _ => return None,
+  };
+  Some(dur)
+}
@@ -0,0 +1,145 @@
+use super::*;
+use std::collections::HashMap;
+
+// how the ref names pointing at a commit are displayed
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DecorateMode {
+  No,
+  Short,
+  Full,
+}
+
+impl DecorateMode {
+  pub const VALUES: [&'static str; 3] = ["short", "full", "no"];
+
+  pub fn from(s: &str) -> DecorateMode {
+    match s {
+      "short" => DecorateMode::Short,
+      "full" => DecorateMode::Full,
+      "no" => DecorateMode::No,
+      _ => err_exit!("Unknown decorate mode: {}", s),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum RefKind {
+  Head,
+  Branch,
+  Remote,
+  Tag,
+  Other,
+}
+
+pub struct Decorations {
+  refs: HashMap<Oid, Vec<(RefKind, String)>>,
+}
+
+impl Decorations {
+  // collect the ref names of the repo by the commit they point to
+  pub fn new(repo: &Repository, mode: DecorateMode) -> Decorations {
+    let mut refs: HashMap<Oid, Vec<(RefKind, String)>> = HashMap::new();
+    let name_of = |r: &Reference| -> Option<String> {
+      match mode {
+        DecorateMode::Full => r.name().map(String::from),
+        _ => r.shorthand().map(String::from),
+      }
+    };
+
+    // HEAD goes first, pointing to the current branch if it is not detached
+    let mut head_branch = None;
+    if let Ok(head) = repo.head() {
+      if let Ok(commit) = head.peel_to_commit() {
+        let label = if head.is_branch() {
+          head_branch = head.name().map(String::from);
+          format!("HEAD -> {}", name_of(&head).unwrap_or_default())
+        } else {
+          String::from("HEAD")
+        };
+        refs
+          .entry(commit.id())
+          .or_default()
+          .push((RefKind::Head, label));
+      }
+    }
+
+    let references = match repo.references() {
+      Ok(r) => r,
+      Err(_) => return Decorations { refs },
+    };
+    for r in references.filter_map(|r| r.ok()) {
+      if r.name().is_some() && r.name() == head_branch.as_deref() {
+        continue;
+      }
+      let (commit, name) = match (r.peel_to_commit(), name_of(&r)) {
+        (Ok(c), Some(n)) => (c, n),
+        _ => continue,
+      };
+      let entry = if r.is_tag() {
+        (RefKind::Tag, format!("tag: {}", name))
+      } else if r.is_branch() {
+        (RefKind::Branch, name)
+      } else if r.is_remote() {
+        (RefKind::Remote, name)
+      } else {
+        (RefKind::Other, name)
+      };
+      refs.entry(commit.id()).or_default().push(entry);
+    }
+    Decorations { refs }
+  }
+
+  // print the ref names of the commit like ` (HEAD -> main, tag: v1.2, origin/main)`
+  pub fn print(&self, id: Oid) {
+    let names = match self.refs.get(&id) {
+      Some(n) => n,
+      None => return,
+    };
+    print!(" {}", "(".yellow());
+    for (i, (kind, name)) in names.iter().enumerate() {
+      if i > 0 {
+        print!("{}", ", ".yellow());
+      }
+      match kind {
+        RefKind::Head => print!("{}", name.cyan()),
+        RefKind::Branch => print!("{}", name.green()),
+        RefKind::Remote => print!("{}", name.red()),
+        RefKind::Tag => print!("{}", name.yellow()),
+        RefKind::Other => print!("{}", name.purple()),
+      }
+    }
+    print!("{}", ")".yellow());
+  }
+}
+
+// the nearest tag reachable from a commit like `git describe --tags`, e.g. "v1.2-14-g1234567"
+// the options are built once and shared by the repos, describe itself is repo local
+pub struct TagDescriber {
+  options: DescribeOptions,
+  format: DescribeFormatOptions,
+}
+
+impl TagDescriber {
+  pub fn new() -> TagDescriber {
+    let mut options = DescribeOptions::new();
+    options.describe_tags();
+    let mut format = DescribeFormatOptions::new();
+    format.abbreviated_size(7);
+    TagDescriber { options, format }
+  }
+
+  // None if no tag can reach the commit
+  pub fn describe(&self, commit: &Commit) -> Option<String> {
+    commit
+      .as_object()
+      .describe(&self.options)
+      .and_then(|d| d.format(Some(&self.format)))
+      .ok()
+  }
+
+  pub fn print(&self, commit: &Commit) {
+    if let Some(d) = self.describe(commit) {
+      print!(" {}", format!("({})", d).yellow());
+    }
+  }
+}
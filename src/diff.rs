@@ -0,0 +1,273 @@
+use super::*;
+use clap::*;
+use std::fs;
+
+pub struct DiffArgs {
+  no_index: Option<(PathBuf, PathBuf)>,
+  cached: bool,
+  diff_filter: DiffFilter,
+  unified: Option<u32>,
+  ignore_all_space: bool,
+  ignore_space_change: bool,
+  ignore_blank_lines: bool,
+}
+
+impl DiffArgs {
+  pub fn build_arg() -> Command {
+    Command::new("diff")
+      .about("Show changes in the working tree across all submodules")
+      .arg(
+        Arg::new("no-index")
+          .long("no-index")
+          .action(ArgAction::SetTrue)
+          .requires("paths")
+          .help("Compare the two given paths on the filesystem, no repo is needed"),
+      )
+      .arg(
+        Arg::new("cached")
+          .long("cached")
+          .visible_alias("staged")
+          .action(ArgAction::SetTrue)
+          .conflicts_with("no-index")
+          .help("Show the changes staged for the next commit, HEAD against the index"),
+      )
+      .arg(
+        Arg::new("diff-filter")
+          .long("diff-filter")
+          .short('f')
+          .help("Filter changes with it's status, the same letters as status"),
+      )
+      .arg(
+        Arg::new("unified")
+          .long("unified")
+          .short('U')
+          .value_name("n")
+          .help("Show n lines of context instead of 3"),
+      )
+      .arg(
+        Arg::new("ignore-all-space")
+          .long("ignore-all-space")
+          .short('w')
+          .action(ArgAction::SetTrue)
+          .help("Ignore whitespace when comparing lines"),
+      )
+      .arg(
+        Arg::new("ignore-space-change")
+          .long("ignore-space-change")
+          .short('b')
+          .action(ArgAction::SetTrue)
+          .help("Ignore changes in amount of whitespace"),
+      )
+      .arg(
+        Arg::new("ignore-blank-lines")
+          .long("ignore-blank-lines")
+          .action(ArgAction::SetTrue)
+          .help("Ignore changes whose lines are all blank"),
+      )
+      .arg(minimal_arg())
+      .arg(crlf_arg())
+      .arg(
+        Arg::new("paths")
+          .action(ArgAction::Append)
+          .num_args(2)
+          .value_names(["path-a", "path-b"])
+          .requires("no-index")
+          .help("The files or directories to compare with --no-index"),
+      )
+  }
+
+  // the --no-index mode doesn't work on a repo
+  pub fn is_no_index(&self) -> bool {
+    self.no_index.is_some()
+  }
+}
+
+impl From<&clap::ArgMatches> for DiffArgs {
+  fn from(matches: &clap::ArgMatches) -> DiffArgs {
+    let no_index = match matches.get_many::<String>("paths") {
+      Some(paths) if matches.get_flag("no-index") => {
+        let paths: Vec<PathBuf> = paths.map(PathBuf::from).collect();
+        Some((paths[0].clone(), paths[1].clone()))
+      }
+      _ => None,
+    };
+    DiffArgs {
+      no_index,
+      cached: matches.get_flag("cached"),
+      diff_filter: match matches.get_one::<String>("diff-filter") {
+        Some(s) => DiffFilter::from(s),
+        None => DiffFilter::default(),
+      },
+      unified: matches.get_one::<String>("unified").map(|s| {
+        s.parse::<u32>()
+          .unwrap_or_else(|e| err_exit!("Error while parsing --unified option: {}", e))
+      }),
+      ignore_all_space: matches.get_flag("ignore-all-space"),
+      ignore_space_change: matches.get_flag("ignore-space-change"),
+      ignore_blank_lines: matches.get_flag("ignore-blank-lines"),
+    }
+  }
+}
+
+// the files under the directory, relative to it
+fn list_dir_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), GitSubError> {
+  for entry in fs::read_dir(dir)?.filter_map(|e| e.ok()) {
+    let path = entry.path();
+    if path.is_dir() {
+      list_dir_files(root, &path, files)?;
+    } else if let Ok(p) = path.strip_prefix(root) {
+      files.push(p.to_owned());
+    }
+  }
+  Ok(())
+}
+
+// the path in the patch headers, like git the root of an absolute path is left out
+// so `/tmp/a` is shown as `a/tmp/a` instead of `a//tmp/a`
+fn header_path(path: &Path) -> PathBuf {
+  path
+    .components()
+    .filter(|c| !matches!(c, Component::Prefix(_) | Component::RootDir))
+    .collect()
+}
+
+// print the patch between two files, either of them may be missing
+// return if they are different
+fn diff_files(old: Option<&Path>, new: Option<&Path>) -> Result<bool, GitSubError> {
+  let read = |path: Option<&Path>| match path {
+    Some(p) => fs::read(p),
+    None => Ok(Vec::new()),
+  };
+  let (old_buf, new_buf) = (read(old)?, read(new)?);
+  let old_header = old.or(new).map(header_path);
+  let new_header = new.or(old).map(header_path);
+  let mut patch = Patch::from_buffers(
+    &old_buf,
+    old_header.as_deref(),
+    &new_buf,
+    new_header.as_deref(),
+    Some(&mut patch_options(&old_buf, &new_buf, "")),
+  )?;
+  if patch.delta().status() == Delta::Unmodified {
+    return Ok(false);
+  }
+  // a file on one side only is added or deleted, its missing side is /dev/null like git
+  let (added, deleted) = (old.is_none(), new.is_none());
+  patch.print(&mut |delta, hunk, line| {
+    if line.origin() == 'F' {
+      print!("{}", file_header(line.content(), added, deleted));
+      true
+    } else {
+      print_callback(delta, hunk, line)
+    }
+  })?;
+  Ok(true)
+}
+
+// compare two paths like `git diff --no-index`, directories are compared file by file
+// return if there is any difference
+fn diff_no_index(old: &Path, new: &Path) -> Result<bool, GitSubError> {
+  if !old.is_dir() || !new.is_dir() {
+    if old.is_dir() || new.is_dir() {
+      return Err(GitSubError::Io(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "Can't compare a file with a directory",
+      )));
+    }
+    return diff_files(Some(old), Some(new));
+  }
+  let (mut old_files, mut new_files) = (Vec::new(), Vec::new());
+  list_dir_files(old, old, &mut old_files)?;
+  list_dir_files(new, new, &mut new_files)?;
+  let mut files = old_files.clone();
+  files.extend(new_files.iter().cloned());
+  files.sort();
+  files.dedup();
+
+  let mut changed = false;
+  for file in files.iter() {
+    let old_file = old.join(file);
+    let new_file = new.join(file);
+    changed |= diff_files(
+      Some(old_file.as_path()).filter(|_| old_files.contains(file)),
+      Some(new_file.as_path()).filter(|_| new_files.contains(file)),
+    )?;
+  }
+  Ok(changed)
+}
+
+// recursively print the changes in the working tree of the repo and its submodules
+// the paths in the patches are relative to the working path to tell the submodules apart
+// with --cached the index is compared to HEAD instead, and the staged submodule pointers
+// are shown as the `Subproject commit` lines
+fn diff_repo(repo: &Repository, work_dir: &Path, args: &DiffArgs) -> Result<(), GitSubError> {
+  let repo_dir = repo
+    .workdir()
+    .and_then(|p| p.canonicalize().ok())
+    .unwrap_or_default();
+  let prefix = patch_prefix(&repo_dir, work_dir);
+  let mut opts = DiffOptions::new();
+  opts
+    .ignore_submodules(!args.cached)
+    .ignore_whitespace(args.ignore_all_space)
+    .ignore_whitespace_change(args.ignore_space_change)
+    .ignore_blank_lines(args.ignore_blank_lines)
+    .minimal(is_minimal_diff())
+    .old_prefix(format!("a/{}", prefix))
+    .new_prefix(format!("b/{}", prefix));
+  if let Some(n) = args.unified {
+    opts.context_lines(n);
+  }
+  let diff = if args.cached {
+    // an unborn HEAD is compared as an empty tree, like git
+    let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+    let mut diff = repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut opts))?;
+    find_renames(repo, &mut diff);
+    diff
+  } else {
+    repo.diff_index_to_workdir(None, Some(&mut opts))?
+  };
+  if diff
+    .deltas()
+    .any(|d| args.diff_filter.test_delta(d.status()))
+  {
+    println!(
+      "{}",
+      format!("Repo: {}", repo_display_path(repo, work_dir)).bright_blue()
+    );
+    diff.print(DiffFormat::Patch, |delta, hunk, line| {
+      !args.diff_filter.test_delta(delta.status()) || print_callback(delta, hunk, line)
+    })?;
+  }
+
+  for sub in repo.submodules()?.iter() {
+    match sub.open() {
+      Ok(r) => diff_repo(&r, work_dir, args)?,
+      Err(e) if is_strict() => {
+        return Err(GitSubError::SubmoduleOpen(
+          submodule_display_path(repo, sub),
+          e,
+        ))
+      }
+      Err(e) => {
+        let path = submodule_display_path(repo, sub);
+        eprintln!("Open submodule {} failed: {}", path, e.message());
+      }
+    }
+  }
+  Ok(())
+}
+
+// exit with 1 if the paths are different in the --no-index mode, like git
+pub fn diff_paths(args: &DiffArgs) -> Result<(), GitSubError> {
+  if let Some((ref old, ref new)) = args.no_index {
+    if diff_no_index(old, new)? {
+      std::process::exit(1);
+    }
+  }
+  Ok(())
+}
+
+pub fn diff(repo: Repository, work_dir: &Path, args: DiffArgs) -> Result<(), GitSubError> {
+  diff_repo(&repo, work_dir, &args)
+}
@@ -63,4 +63,16 @@ impl DiffFilter {
       return self.unknown;
     }
   }
+
+  // the same filter for the deltas of a diff, which have no index and working tree sides
+  pub fn test_delta(&self, delta: git2::Delta) -> bool {
+    match delta {
+      git2::Delta::Added | git2::Delta::Untracked => self.add,
+      git2::Delta::Modified => self.modified,
+      git2::Delta::Deleted => self.deleted,
+      git2::Delta::Renamed | git2::Delta::Copied => self.rename,
+      git2::Delta::Typechange => self.type_changed,
+      _ => self.unknown,
+    }
+  }
 }
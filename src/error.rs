@@ -0,0 +1,80 @@
+use std::fmt;
+
+// the failures of the subcommands which can be told apart by the caller
+#[derive(Debug)]
+pub enum GitSubError {
+  // the working path is not in a git repo
+  NotARepo(git2::Error),
+  // the submodule at the path can't be opened, e.g. it's not initialized
+  SubmoduleOpen(String, git2::Error),
+  // the revision can't be resolved to a commit
+  RevParse(String, git2::Error),
+  // the path given on the command line doesn't exist or isn't in the repo
+  NoSuchPath(String),
+  OutsideRepo(String),
+  // the repo has no working tree to find the path in
+  BareRepo,
+  // the foreach command exited with a failure in the submodule at the path
+  CommandFailed(String),
+  // the foreach command may throw away work and isn't confirmed by --yes
+  Destructive(String),
+  Git(git2::Error),
+  Io(std::io::Error),
+}
+
+impl GitSubError {
+  // like git, a bad repo or revision is fatal with 128, other failures exit with 1
+  pub fn exit_code(&self) -> i32 {
+    match self {
+      GitSubError::NotARepo(_) | GitSubError::RevParse(..) => 128,
+      _ => 1,
+    }
+  }
+}
+
+impl fmt::Display for GitSubError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      GitSubError::NotARepo(e) => write!(f, "Open repo failed, not a git repo? {}", e.message()),
+      GitSubError::SubmoduleOpen(path, e) => {
+        write!(f, "Open submodule {} failed: {}", path, e.message())
+      }
+      GitSubError::RevParse(rev, e) => {
+        write!(f, "Can't find the revision {}: {}", rev, e.message())
+      }
+      GitSubError::NoSuchPath(path) => write!(f, "No such path {}", path),
+      GitSubError::OutsideRepo(path) => write!(f, "{} is outside of the repo", path),
+      GitSubError::BareRepo => write!(f, "The repo has no working tree"),
+      GitSubError::CommandFailed(path) => {
+        write!(f, "Stopping at '{}'; command returned non-zero status.", path)
+      }
+      GitSubError::Destructive(command) => write!(
+        f,
+        "`{}` may throw away changes in every submodule, check it with --dry-run and confirm with --yes",
+        command
+      ),
+      GitSubError::Git(e) => write!(f, "{}", e.message()),
+      GitSubError::Io(e) => write!(f, "{}", e),
+    }
+  }
+}
+
+impl std::error::Error for GitSubError {}
+
+impl From<git2::Error> for GitSubError {
+  fn from(e: git2::Error) -> GitSubError {
+    GitSubError::Git(e)
+  }
+}
+
+impl From<std::io::Error> for GitSubError {
+  fn from(e: std::io::Error) -> GitSubError {
+    GitSubError::Io(e)
+  }
+}
+
+// print the error and exit with its code
+pub fn exit_with(e: GitSubError) -> ! {
+  crate::color::print_error(&e.to_string());
+  std::process::exit(e.exit_code());
+}
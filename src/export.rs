@@ -0,0 +1,119 @@
+use super::*;
+use chrono::prelude::*;
+use clap::*;
+
+pub struct ExportArgs {
+  relative: bool,
+}
+
+impl ExportArgs {
+  pub fn build_arg() -> Command {
+    Command::new("export")
+      .about("Print a json manifest of the submodules and their recorded commits")
+      .arg(
+        Arg::new("relative")
+          .long("relative")
+          .action(ArgAction::SetTrue)
+          .help("Write the paths relative to the root repo so the manifest is portable"),
+      )
+  }
+}
+
+impl From<&clap::ArgMatches> for ExportArgs {
+  fn from(matches: &clap::ArgMatches) -> ExportArgs {
+    ExportArgs {
+      relative: matches.get_flag("relative"),
+    }
+  }
+}
+
+// `rel_path` is the path of the repo relative to the root repo, e.g. "sub/a/"
+// without --relative the paths are relative to the base path like the other subcommands
+fn collect_entries(
+  repo: &Repository,
+  rel_path: &str,
+  base_path: &Path,
+  args: &ExportArgs,
+  entries: &mut Vec<String>,
+) -> Result<(), GitSubError> {
+  let repo_dir = repo
+    .workdir()
+    .and_then(|p| p.canonicalize().ok())
+    .unwrap_or_default();
+  for sub in repo.submodules()?.iter() {
+    let sub_path = format!(
+      "{}{}",
+      rel_path,
+      sub.path().display().to_string().replace("\\", "/")
+    );
+    let path = if args.relative {
+      sub_path.clone()
+    } else {
+      relative_path(&repo_dir.join(sub.path()), base_path)
+    };
+    let fields = [
+      ("path", json::json_str(&path)),
+      (
+        "commit",
+        sub
+          .head_id()
+          .map(|id| json::json_str(&id.to_string()))
+          .unwrap_or_else(|| String::from("null")),
+      ),
+      (
+        "url",
+        sub
+          .url()
+          .map(|u| json::json_str(&resolve_url(repo, u)))
+          .unwrap_or_else(|| String::from("null")),
+      ),
+      (
+        "branch",
+        sub
+          .branch()
+          .map(json::json_str)
+          .unwrap_or_else(|| String::from("null")),
+      ),
+    ];
+    entries.push(json::json_object(&fields, true, "    "));
+    match sub.open() {
+      Ok(sub_repo) => collect_entries(
+        &sub_repo,
+        &format!("{}/", sub_path),
+        base_path,
+        args,
+        entries,
+      )?,
+      Err(e) if is_strict() => return Err(GitSubError::SubmoduleOpen(sub_path, e)),
+      Err(e) => eprintln!(
+        "Warning: can't open submodule {}, its submodules are left out: {}",
+        sub_path,
+        e.message()
+      ),
+    }
+  }
+  Ok(())
+}
+
+pub fn export(repo: Repository, work_dir: &Path, args: ExportArgs) -> Result<(), GitSubError> {
+  let head = repo.head()?.peel_to_commit()?;
+  let root_str = repo_display_path(&repo, work_dir);
+  let mut entries = Vec::new();
+  collect_entries(&repo, "", work_dir, &args, &mut entries)?;
+  let submodules = if entries.is_empty() {
+    String::from("[]")
+  } else {
+    format!("[\n    {}\n  ]", entries.join(",\n    "))
+  };
+  let fields = [
+    ("generated_at", json::json_str(&Local::now().to_rfc3339())),
+    ("head", json::json_str(&head.id().to_string())),
+    (
+      "root",
+      json::json_str(if args.relative { "." } else { &root_str }),
+    ),
+    ("submodules", submodules),
+  ];
+  println!("{}", json::json_object(&fields, true, ""));
+  Ok(())
+}
@@ -0,0 +1,199 @@
+use super::*;
+use clap::*;
+
+pub struct FetchArgs {
+  quiet: bool,
+  // the progress is shown only when stderr is a terminal
+  progress: bool,
+}
+
+impl FetchArgs {
+  pub fn build_arg() -> Command {
+    Command::new("fetch")
+      .about("Fetch all submodules from the url and branch configured in .gitmodules")
+      .arg(
+        Arg::new("quiet")
+          .long("quiet")
+          .short('q')
+          .action(ArgAction::SetTrue)
+          .help("Only print the errors"),
+      )
+  }
+}
+
+impl From<&clap::ArgMatches> for FetchArgs {
+  fn from(matches: &clap::ArgMatches) -> FetchArgs {
+    let quiet = matches.get_flag("quiet");
+    FetchArgs {
+      quiet,
+      progress: !quiet && atty::is(atty::Stream::Stderr),
+    }
+  }
+}
+
+// the url of the remote of the current branch like git, otherwise of origin
+fn default_remote_url(parent: &Repository) -> Option<String> {
+  let remote = parent
+    .head()
+    .ok()
+    .filter(|h| h.is_branch())
+    .and_then(|h| h.shorthand().map(String::from))
+    .and_then(|b| {
+      parent
+        .config()
+        .ok()?
+        .get_string(&format!("branch.{}.remote", b))
+        .ok()
+    })
+    .unwrap_or_else(|| String::from("origin"));
+  parent.find_remote(&remote).ok()?.url().map(String::from)
+}
+
+// resolve the relative submodule url (./ or ../) against the url of the parent repo
+// like git, the remote of the parent's branch is used as the base, otherwise its working directory
+// each ../ drops a path component of the base, up to the host of a scp-like url "host:path"
+pub fn resolve_url(parent: &Repository, url: &str) -> String {
+  if !url.starts_with("./") && !url.starts_with("../") {
+    return String::from(url);
+  }
+  let mut base = default_remote_url(parent).unwrap_or_else(|| {
+    parent
+      .workdir()
+      .unwrap_or_else(|| parent.path())
+      .display()
+      .to_string()
+      .replace("\\", "/")
+  });
+  while base.ends_with('/') {
+    base.pop();
+  }
+  let mut rest = url;
+  let mut sep = '/';
+  loop {
+    if let Some(r) = rest.strip_prefix("./") {
+      rest = r;
+    } else if let Some(r) = rest.strip_prefix("../") {
+      rest = r;
+      match base.rfind(['/', ':']) {
+        Some(i) => {
+          sep = if base[i..].starts_with(':') { ':' } else { '/' };
+          base.truncate(i);
+        }
+        None => base.clear(),
+      }
+    } else {
+      break;
+    }
+  }
+  if base.is_empty() {
+    String::from(rest)
+  } else {
+    format!("{}{}{}", base, sep, rest)
+  }
+}
+
+// the branch to fetch, "." follows the current branch of the parent repo
+fn resolve_branch(parent: &Repository, sub: &Submodule) -> Option<String> {
+  match sub.branch() {
+    Some(".") => parent
+      .head()
+      .ok()
+      .filter(|h| h.is_branch())
+      .and_then(|h| h.shorthand().map(String::from)),
+    Some(b) => Some(String::from(b)),
+    None => None,
+  }
+}
+
+// fetch the refspec from the url, showing the progress in place on stderr
+// return the fetched commit and the received bytes
+fn fetch_url(
+  repo: &Repository,
+  url: &str,
+  refspec: &str,
+  sub_path: &str,
+  args: &FetchArgs,
+) -> Result<(Oid, usize), git2::Error> {
+  let mut callbacks = RemoteCallbacks::new();
+  if args.progress {
+    callbacks.transfer_progress(|stats| {
+      let total = stats.total_objects().max(1);
+      eprint!(
+        "\r {}: {}% ({}/{}), {}   ",
+        sub_path,
+        stats.received_objects() * 100 / total,
+        stats.received_objects(),
+        stats.total_objects(),
+        format_size(stats.received_bytes() as u64)
+      );
+      true
+    });
+  }
+  let mut options = FetchOptions::new();
+  options.remote_callbacks(callbacks);
+  let mut remote = repo.remote_anonymous(url)?;
+  let fetched = remote.fetch(&[refspec], Some(&mut options), None);
+  if args.progress {
+    // clear the progress line
+    eprint!("\r{}\r", " ".repeat(sub_path.len() + 40));
+  }
+  fetched?;
+  let bytes = remote.stats().received_bytes();
+  Ok((repo.refname_to_id("FETCH_HEAD")?, bytes))
+}
+
+// return the received bytes of the submodules
+fn fetch_submodules(parent: &Repository, args: &FetchArgs) -> Result<usize, GitSubError> {
+  let mut total = 0;
+  for sub in parent.submodules()?.iter() {
+    let sub_path = submodule_display_path(parent, sub);
+    let url = match sub.url() {
+      Some(u) => resolve_url(parent, u),
+      None => {
+        warn_or_exit!("Skip {}: no url is configured", sub_path);
+        continue;
+      }
+    };
+    let sub_repo = match sub.open() {
+      Ok(r) => r,
+      Err(e) if is_strict() => return Err(GitSubError::SubmoduleOpen(sub_path, e)),
+      Err(e) => {
+        eprintln!("Skip {}: open repo failed: {}", sub_path, e.message());
+        continue;
+      }
+    };
+    let branch = resolve_branch(parent, sub);
+    let refspec = match branch {
+      Some(ref b) => format!("refs/heads/{}", b),
+      None => String::from("HEAD"),
+    };
+    if !args.quiet {
+      println!(
+        "Fetching {} from {} ({})",
+        sub_path.bright_blue(),
+        url,
+        branch.as_deref().unwrap_or("HEAD")
+      );
+    }
+    match fetch_url(&sub_repo, &url, &refspec, &sub_path, args) {
+      Ok((id, bytes)) => {
+        total += bytes;
+        if !args.quiet {
+          println!(" -> {}", &id.to_string()[..7].green());
+        }
+      }
+      Err(e) if is_strict() => return Err(e.into()),
+      Err(e) => eprintln!(" Fetch {} failed: {}", sub_path, e.message()),
+    }
+    total += fetch_submodules(&sub_repo, args)?;
+  }
+  Ok(total)
+}
+
+pub fn fetch(repo: Repository, args: FetchArgs) -> Result<(), GitSubError> {
+  let total = fetch_submodules(&repo, &args)?;
+  if !args.quiet {
+    println!("Received {} in total", format_size(total as u64));
+  }
+  Ok(())
+}
@@ -0,0 +1,369 @@
+use super::*;
+use clap::*;
+use std::process;
+
+// the options of git itself which take the next word as their value
+const GIT_VALUE_OPTIONS: [&str; 5] = ["-C", "-c", "--git-dir", "--work-tree", "--namespace"];
+
+pub struct ForeachArgs {
+  // one word is a shell command, more are the program and its arguments like in git
+  command: Vec<String>,
+  dry_run: bool,
+  yes: bool,
+  recursive: bool,
+  // run in a submodule after its own submodules instead of before
+  post_order: bool,
+}
+
+impl ForeachArgs {
+  pub fn build_arg() -> Command {
+    Command::new("foreach")
+      .about("Run a shell command in each submodule recursively")
+      .arg(
+        Arg::new("dry-run")
+          .long("dry-run")
+          .short('n')
+          .action(ArgAction::SetTrue)
+          .help("Only print the command to run in each submodule"),
+      )
+      .arg(
+        Arg::new("yes")
+          .long("yes")
+          .short('y')
+          .action(ArgAction::SetTrue)
+          .help("Confirm running destructive commands like `git reset --hard`"),
+      )
+      .arg(
+        Arg::new("recursive")
+          .long("recursive")
+          .action(ArgAction::SetTrue)
+          .help("Run in the nested submodules as well, the default"),
+      )
+      .arg(
+        Arg::new("top-level-only")
+          .long("top-level-only")
+          .action(ArgAction::SetTrue)
+          .conflicts_with("recursive")
+          .help("Only run in the submodules of the root repo"),
+      )
+      .arg(
+        Arg::new("pre-order")
+          .long("pre-order")
+          .action(ArgAction::SetTrue)
+          .help("Run in a submodule before its own submodules, the default"),
+      )
+      .arg(
+        Arg::new("post-order")
+          .long("post-order")
+          .action(ArgAction::SetTrue)
+          .conflicts_with("pre-order")
+          .help("Run in a submodule after its own submodules"),
+      )
+      .arg(
+        Arg::new("command")
+          .required(true)
+          .num_args(1..)
+          .trailing_var_arg(true)
+          .allow_hyphen_values(true)
+          .help("The command, $name, $sm_path, $path, $displaypath, $sha1 and $toplevel are set"),
+      )
+  }
+}
+
+impl From<&clap::ArgMatches> for ForeachArgs {
+  fn from(matches: &clap::ArgMatches) -> ForeachArgs {
+    ForeachArgs {
+      command: matches
+        .get_many::<String>("command")
+        .map(|c| c.cloned().collect())
+        .unwrap_or_default(),
+      dry_run: matches.get_flag("dry-run"),
+      yes: matches.get_flag("yes"),
+      recursive: !matches.get_flag("top-level-only"),
+      post_order: matches.get_flag("post-order"),
+    }
+  }
+}
+
+// replace $var and ${var} with the values for the dry run
+fn expand_vars(command: &str, vars: &[(&str, String)]) -> String {
+  let mut expanded = String::from(command);
+  for (name, value) in vars.iter() {
+    expanded = expanded
+      .replace(&format!("${{{}}}", name), value)
+      .replace(&format!("${}", name), value);
+  }
+  expanded
+}
+
+// the word as the shell reads it back, quoted only when it has to be
+fn shell_quote(word: &str) -> String {
+  let plain = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+  if !word.is_empty() && word.chars().all(plain) {
+    String::from(word)
+  } else {
+    format!("'{}'", word.replace('\'', "'\\''"))
+  }
+}
+
+// the command as it's shown, the words are quoted so they can be told apart
+fn display_command(args: &ForeachArgs, vars: &[(&str, String)]) -> String {
+  match args.command.as_slice() {
+    [script] => expand_vars(script, vars),
+    words => words
+      .iter()
+      .map(|w| shell_quote(w))
+      .collect::<Vec<_>>()
+      .join(" "),
+  }
+}
+
+// a single word runs in the shell, more words run the program with the arguments as they are
+fn build_command(words: &[String]) -> process::Command {
+  match words {
+    [script] => shell_command(script),
+    words => {
+      let mut c = process::Command::new(&words[0]);
+      c.args(&words[1..]);
+      c
+    }
+  }
+}
+
+pub fn shell_command(command: &str) -> process::Command {
+  if cfg!(windows) {
+    let mut c = process::Command::new("cmd");
+    c.arg("/C").arg(command);
+    c
+  } else {
+    let mut c = process::Command::new("sh");
+    c.arg("-c").arg(command);
+    c
+  }
+}
+
+// run the command in the submodule, or only print it for the dry run
+fn run_in(
+  work_dir: &Path,
+  display_path: &str,
+  vars: &[(&str, String)],
+  args: &ForeachArgs,
+) -> Result<(), GitSubError> {
+  if args.dry_run {
+    println!(
+      "{}: {}",
+      display_path.bright_blue(),
+      display_command(args, vars)
+    );
+    return Ok(());
+  }
+  println!("Entering '{}'", display_path.bright_blue());
+  let mut command = build_command(&args.command);
+  command.current_dir(work_dir);
+  for (name, value) in vars.iter() {
+    command.env(name, value);
+  }
+  if !command.status()?.success() {
+    return Err(GitSubError::CommandFailed(String::from(display_path)));
+  }
+  Ok(())
+}
+
+// the submodules of a repo are visited in the order of their paths, so scripts see a stable order
+// in pre-order a submodule comes before its own submodules, in post-order after them
+// like git, $toplevel is the working directory of the immediate parent repo
+fn foreach_submodule(parent: &Repository, args: &ForeachArgs) -> Result<(), GitSubError> {
+  let toplevel = parent.workdir().expect("Get workdir failed");
+  let mut subs = parent.submodules()?;
+  subs.sort_by(|a, b| a.path().cmp(b.path()));
+  for sub in subs.iter() {
+    let sm_path = sub.path().display().to_string().replace("\\", "/");
+    // like git, $displaypath is relative to the working path
+    let display_path = submodule_display_path(parent, sub);
+    let sub_repo = match sub.open() {
+      Ok(r) => r,
+      Err(e) if is_strict() => return Err(GitSubError::SubmoduleOpen(display_path, e)),
+      Err(e) => {
+        eprintln!("Open submodule {} failed: {}", display_path, e.message());
+        continue;
+      }
+    };
+    let work_dir = sub_repo.workdir().expect("Get workdir failed").to_owned();
+    let vars = [
+      ("name", String::from(sub.name().unwrap_or_default())),
+      ("sm_path", sm_path.clone()),
+      ("path", sm_path),
+      ("displaypath", display_path.clone()),
+      (
+        "sha1",
+        sub.head_id().map(|id| id.to_string()).unwrap_or_default(),
+      ),
+      (
+        "toplevel",
+        toplevel
+          .display()
+          .to_string()
+          .trim_end_matches('/')
+          .to_string(),
+      ),
+    ];
+
+    if !args.post_order {
+      run_in(&work_dir, &display_path, &vars, args)?;
+    }
+    if args.recursive {
+      foreach_submodule(&sub_repo, args)?;
+    }
+    if args.post_order {
+      run_in(&work_dir, &display_path, &vars, args)?;
+    }
+  }
+  Ok(())
+}
+
+// split the shell command into the words of each simple command, the commands are separated
+// by `;`, `&`, `|` and newlines, and the quotes keep a word together
+fn split_commands(command: &str) -> Vec<Vec<String>> {
+  let mut commands = vec![Vec::new()];
+  let mut word: Option<String> = None;
+  let mut quote = None;
+  let mut chars = command.chars();
+  while let Some(c) = chars.next() {
+    match (quote, c) {
+      (Some(q), c) if c == q => quote = None,
+      (Some('"'), '\\') | (None, '\\') => {
+        if let Some(next) = chars.next() {
+          word.get_or_insert_with(String::new).push(next);
+        }
+      }
+      (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+      (None, '\'' | '"') => {
+        quote = Some(c);
+        word.get_or_insert_with(String::new);
+      }
+      (None, ';' | '&' | '|' | '\n') => {
+        commands.last_mut().unwrap().extend(word.take());
+        commands.push(Vec::new());
+      }
+      (None, c) if c.is_whitespace() => commands.last_mut().unwrap().extend(word.take()),
+      (None, c) => word.get_or_insert_with(String::new).push(c),
+    }
+  }
+  commands.last_mut().unwrap().extend(word.take());
+  commands.retain(|c| !c.is_empty());
+  commands
+}
+
+// a short option cluster like `-xfd` which includes `f`
+fn has_short_force(word: &str) -> bool {
+  !word.starts_with("--") && word.starts_with('-') && word.contains('f')
+}
+
+// check if the words run a git command which throws away work:
+// `reset --hard`, `clean`, `checkout` or `push` forced, or `push` of a `+refspec`
+fn is_destructive(words: &[String]) -> bool {
+  // the environment assignments before the command
+  let mut words = words.iter().skip_while(|w| w.contains('='));
+  let program = words.next().map(String::as_str).unwrap_or_default();
+  let program = program.rsplit(['/', '\\']).next().unwrap_or_default();
+  if program != "git" && program != "git.exe" {
+    return false;
+  }
+  let subcommand = loop {
+    match words.next().map(String::as_str) {
+      Some(w) if GIT_VALUE_OPTIONS.contains(&w) => {
+        words.next();
+      }
+      Some(w) if w.starts_with('-') => {}
+      Some(w) => break w,
+      None => return false,
+    }
+  };
+  let args: Vec<&str> = words.map(String::as_str).collect();
+  let forced = || args.iter().any(|a| has_short_force(a) || *a == "--force");
+  match subcommand {
+    "reset" => args.contains(&"--hard"),
+    "clean" | "checkout" => forced(),
+    "push" => {
+      forced()
+        || args
+          .iter()
+          .any(|a| a.starts_with("--force-with-lease") || a.starts_with('+'))
+    }
+    _ => false,
+  }
+}
+
+pub fn foreach(repo: Repository, args: ForeachArgs) -> Result<(), GitSubError> {
+  if !args.dry_run && !args.yes {
+    let commands = match args.command.as_slice() {
+      [script] => split_commands(script),
+      words => vec![words.to_vec()],
+    };
+    if let Some(c) = commands.iter().find(|c| is_destructive(c)) {
+      let shown: Vec<String> = c.iter().map(|w| shell_quote(w)).collect();
+      return Err(GitSubError::Destructive(shown.join(" ")));
+    }
+  }
+  foreach_submodule(&repo, &args)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn destructive(command: &str) -> bool {
+    split_commands(command).iter().any(|c| is_destructive(c))
+  }
+
+  #[test]
+  fn quotes_only_the_words_which_need_it() {
+    assert_eq!(shell_quote("git"), "git");
+    assert_eq!(shell_quote("origin/main"), "origin/main");
+    assert_eq!(shell_quote("a b"), "'a b'");
+    assert_eq!(shell_quote(""), "''");
+    assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    assert_eq!(shell_quote("$name"), "'$name'");
+  }
+
+  #[test]
+  fn finds_the_destructive_spellings() {
+    for command in [
+      "git reset --hard",
+      "git reset  --hard HEAD~1",
+      "git clean -xfd",
+      "git clean -ffdx",
+      "git clean --force",
+      "git checkout -f main",
+      "git push origin +main",
+      "git push -uf origin main",
+      "git push --force-with-lease",
+      "git -C sub reset --hard",
+      "git -c core.x=y --no-pager clean -f",
+      "GIT_TRACE=1 /usr/bin/git clean -fd",
+      "git status && git reset --hard",
+      "echo start; git clean -fdx",
+    ] {
+      assert!(destructive(command), "{}", command);
+    }
+  }
+
+  #[test]
+  fn leaves_the_other_commands_alone() {
+    for command in [
+      "git status",
+      "git reset --soft HEAD~1",
+      "git clean -n",
+      "git clean -nd",
+      "git checkout main",
+      "git push origin main",
+      "git fetch --force",
+      "echo git reset --hard",
+      "echo 'git clean -f'",
+      "git commit -m 'reset --hard later'",
+      "grep -f patterns file",
+    ] {
+      assert!(!destructive(command), "{}", command);
+    }
+  }
+}
@@ -0,0 +1,164 @@
+use super::*;
+use std::collections::HashMap;
+
+// the colors given to the repos in turn
+const PALETTE: [&str; 6] = ["green", "yellow", "cyan", "purple", "blue", "red"];
+
+fn paint(text: &str, color: &str) -> ColoredString {
+  match color {
+    "green" => text.green(),
+    "yellow" => text.yellow(),
+    "cyan" => text.cyan(),
+    "purple" => text.purple(),
+    "blue" => text.bright_blue(),
+    _ => text.red(),
+  }
+}
+
+// a color per repo by the order the repos are collected, so it's stable between runs
+pub struct RepoColors {
+  colors: HashMap<PathBuf, &'static str>,
+}
+
+impl RepoColors {
+  pub fn new(repos: &[Repository]) -> RepoColors {
+    RepoColors {
+      colors: repos
+        .iter()
+        .filter_map(|r| r.workdir())
+        .enumerate()
+        .map(|(i, p)| (p.to_path_buf(), PALETTE[i % PALETTE.len()]))
+        .collect(),
+    }
+  }
+
+  pub fn paint(&self, repo_path: &Path, text: &str) -> ColoredString {
+    match self.colors.get(repo_path) {
+      Some(color) => paint(text, color),
+      None => text.default(),
+    }
+  }
+}
+
+// the lanes of `log --graph`, each waits for the next commit of a chain in a repo
+// the commits of different repos never share a lane, so each submodule keeps its own chains
+pub struct Graph {
+  lanes: Vec<Option<(Oid, PathBuf)>>,
+  // the lanes opened by the last merge, drawn after its line
+  opened: Vec<usize>,
+}
+
+impl Graph {
+  pub fn new() -> Graph {
+    Graph {
+      lanes: Vec::new(),
+      opened: Vec::new(),
+    }
+  }
+
+  fn print_symbol(&self, i: usize, symbol: &str, colors: Option<&RepoColors>) {
+    match (colors, self.lanes.get(i).and_then(Option::as_ref)) {
+      (Some(c), Some((_, p))) => print!("{}", c.paint(p, symbol)),
+      _ => print!("{}", symbol),
+    }
+  }
+
+  // print the lanes of the commit line, the commit is drawn as `*` in its lane
+  fn print_lanes(&self, col: usize, colors: Option<&RepoColors>) {
+    for (i, lane) in self.lanes.iter().enumerate() {
+      let symbol = if i == col {
+        "*"
+      } else if lane.is_some() {
+        "|"
+      } else {
+        " "
+      };
+      self.print_symbol(i, symbol, colors);
+      print!(" ");
+    }
+  }
+
+  // print a line between the commits where the marked lanes bend to the left by `symbol`
+  fn print_connector(&self, marked: &[usize], symbol: &str, colors: Option<&RepoColors>) {
+    for (i, lane) in self.lanes.iter().enumerate() {
+      if lane.is_some() && !marked.contains(&i) {
+        self.print_symbol(i, "|", colors);
+      } else {
+        print!(" ");
+      }
+      if marked.contains(&(i + 1)) {
+        self.print_symbol(i + 1, symbol, colors);
+      } else {
+        print!(" ");
+      }
+    }
+    println!();
+  }
+
+  // print the lanes before the commit line, the commit goes to the lane waiting for it
+  // then the lane waits for the first parent, and the other parents get lanes of their own
+  pub fn print_row(&mut self, commit: &Commit, repo_path: &Path, colors: Option<&RepoColors>) {
+    let waiting = |lane: &Option<(Oid, PathBuf)>| match lane {
+      Some((id, p)) => *id == commit.id() && p == repo_path,
+      None => false,
+    };
+    let col = match self.lanes.iter().position(waiting) {
+      Some(i) => i,
+      None => match self.lanes.iter().position(Option::is_none) {
+        Some(i) => i,
+        None => {
+          self.lanes.push(None);
+          self.lanes.len() - 1
+        }
+      },
+    };
+
+    // the other lanes waiting for the commit join its lane here
+    let joined: Vec<usize> = (0..self.lanes.len())
+      .filter(|i| *i != col && waiting(&self.lanes[*i]))
+      .collect();
+    if !joined.is_empty() {
+      self.print_connector(&joined, "/", colors);
+      for i in joined {
+        self.lanes[i] = None;
+      }
+    }
+
+    self.lanes[col] = Some((commit.id(), repo_path.to_path_buf()));
+    while let Some(None) = self.lanes.last() {
+      self.lanes.pop();
+    }
+    self.print_lanes(col, colors);
+
+    let mut parents = commit.parent_ids();
+    self.lanes[col] = parents.next().map(|id| (id, repo_path.to_path_buf()));
+    self.opened.clear();
+    for id in parents {
+      let lane = Some((id, repo_path.to_path_buf()));
+      if self.lanes.contains(&lane) {
+        continue;
+      }
+      let i = match self.lanes.iter().skip(col + 1).position(Option::is_none) {
+        Some(i) => col + 1 + i,
+        None => {
+          self.lanes.push(None);
+          self.lanes.len() - 1
+        }
+      };
+      self.lanes[i] = lane;
+      self.opened.push(i);
+    }
+    while let Some(None) = self.lanes.last() {
+      self.lanes.pop();
+    }
+  }
+
+  // print the lanes opened by the merge after its line
+  pub fn print_after(&mut self, colors: Option<&RepoColors>) {
+    if self.opened.is_empty() {
+      return;
+    }
+    let opened = std::mem::take(&mut self.opened);
+    self.print_connector(&opened, "\\", colors);
+  }
+}
@@ -0,0 +1,43 @@
+// minimal helpers to emit json without pulling in serde
+
+// quote and escape the string as a json string literal
+pub fn json_str(s: &str) -> String {
+  let mut out = String::with_capacity(s.len() + 2);
+  out.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\r' => out.push_str("\\r"),
+      '\t' => out.push_str("\\t"),
+      c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+  out
+}
+
+// format the fields as a json object, the values must be json already
+// the pretty form puts each field on its own line, indented under `indent`
+pub fn json_object(fields: &[(&str, String)], pretty: bool, indent: &str) -> String {
+  if fields.is_empty() {
+    return String::from("{}");
+  }
+  let items: Vec<String> = fields
+    .iter()
+    .map(|(k, v)| {
+      if pretty {
+        format!("{}  {}: {}", indent, json_str(k), v)
+      } else {
+        format!("{}:{}", json_str(k), v)
+      }
+    })
+    .collect();
+  if pretty {
+    format!("{{\n{}\n{}}}", items.join(",\n"), indent)
+  } else {
+    format!("{{{}}}", items.join(","))
+  }
+}
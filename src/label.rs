@@ -0,0 +1,159 @@
+use super::*;
+use clap::*;
+
+const COLOR_VALUES: [&str; 7] = [
+  "red", "green", "purple", "cyan", "blue", "yellow", "default",
+];
+
+fn paint(text: &str, color: &str) -> ColoredString {
+  match color {
+    "red" => text.red(),
+    "green" => text.green(),
+    "purple" => text.purple(),
+    "cyan" => text.cyan(),
+    "blue" => text.bright_blue(),
+    "yellow" => text.yellow(),
+    _ => text.default(),
+  }
+}
+
+// the symbols and colors of the change labels, the defaults follow git
+pub struct LabelTheme {
+  added: String,
+  modified: String,
+  deleted: String,
+  renamed: String,
+  typechange: String,
+  copied: String,
+  untracked: String,
+  ignored: String,
+  staged_color: String,
+  unstaged_color: String,
+}
+
+// the arguments to customize the labels, shared by status and log
+pub fn label_args() -> Vec<Arg> {
+  let symbol = |name: &'static str, default: &'static str| {
+    Arg::new(name)
+      .long(name)
+      .value_name("symbol")
+      .default_value(default)
+      .help(format!("The label of {} files", &name[6..]))
+  };
+  let color = |name: &'static str, default: &'static str, help: &'static str| {
+    Arg::new(name)
+      .long(name)
+      .value_name("color")
+      .value_parser(COLOR_VALUES)
+      .default_value(default)
+      .help(help)
+  };
+  vec![
+    symbol("label-added", "A"),
+    symbol("label-modified", "M"),
+    symbol("label-deleted", "D"),
+    symbol("label-renamed", "R"),
+    symbol("label-typechange", "T"),
+    symbol("label-copied", "C"),
+    symbol("label-untracked", "?"),
+    symbol("label-ignored", "!"),
+    color(
+      "label-staged-color",
+      "green",
+      "The color of staged labels, and of added, renamed, copied and type changed files in log",
+    ),
+    color(
+      "label-unstaged-color",
+      "red",
+      "The color of unstaged labels, and of modified and deleted files in log",
+    ),
+  ]
+}
+
+impl From<&clap::ArgMatches> for LabelTheme {
+  fn from(matches: &clap::ArgMatches) -> LabelTheme {
+    // the labels are aligned in columns, so each symbol takes a single character
+    let get = |name: &str| {
+      let s = matches.get_one::<String>(name).cloned().unwrap_or_default();
+      if s.chars().count() != 1 {
+        err_exit!("The value of --{} must be a single character: {}", name, s);
+      }
+      s
+    };
+    LabelTheme {
+      added: get("label-added"),
+      modified: get("label-modified"),
+      deleted: get("label-deleted"),
+      renamed: get("label-renamed"),
+      typechange: get("label-typechange"),
+      copied: get("label-copied"),
+      untracked: get("label-untracked"),
+      ignored: get("label-ignored"),
+      staged_color: matches
+        .get_one::<String>("label-staged-color")
+        .cloned()
+        .unwrap_or_default(),
+      unstaged_color: matches
+        .get_one::<String>("label-unstaged-color")
+        .cloned()
+        .unwrap_or_default(),
+    }
+  }
+}
+
+impl LabelTheme {
+  // the two column label of `git status --short`, staged changes go to the first column
+  pub fn status_label(&self, status: Status) -> ColoredString {
+    let staged = |s: &str| paint(&format!("{} ", s), &self.staged_color);
+    let unstaged = |s: &str| paint(&format!(" {}", s), &self.unstaged_color);
+    let twice = |s: &str| paint(&format!("{}{}", s, s), &self.unstaged_color);
+    if status.is_index_new() {
+      match color_override("status.added") {
+        Some(color) => format!("{} ", self.added).paint(color),
+        None => staged(&self.added),
+      }
+    } else if status.is_index_modified() {
+      staged(&self.modified)
+    } else if status.is_index_deleted() {
+      staged(&self.deleted)
+    } else if status.is_index_renamed() {
+      staged(&self.renamed)
+    } else if status.is_index_typechange() {
+      staged(&self.typechange)
+    } else if status.is_wt_new() {
+      twice(&self.untracked)
+    } else if status.is_wt_modified() {
+      unstaged(&self.modified)
+    } else if status.is_wt_deleted() {
+      unstaged(&self.deleted)
+    } else if status.is_wt_typechange() {
+      unstaged(&self.typechange)
+    } else if status.is_wt_renamed() {
+      unstaged(&self.renamed)
+    } else if status.is_ignored() {
+      twice(&self.ignored)
+    } else {
+      twice(&self.untracked)
+    }
+  }
+
+  // the single letter label of a file changed by a commit
+  pub fn delta_label(&self, delta: Delta) -> ColoredString {
+    match delta {
+      Delta::Added => match color_override("status.added") {
+        Some(color) => self.added.paint(color),
+        None => paint(&self.added, &self.staged_color),
+      },
+      Delta::Conflicted => "C".red(),
+      Delta::Copied => paint(&self.copied, &self.staged_color),
+      Delta::Deleted => paint(&self.deleted, &self.unstaged_color),
+      Delta::Ignored => "I".red(),
+      Delta::Modified => paint(&self.modified, &self.unstaged_color),
+      Delta::Renamed => paint(&self.renamed, &self.staged_color),
+      Delta::Typechange => paint(&self.typechange, &self.staged_color),
+      Delta::Unmodified => "U".green(),
+      Delta::Unreadable => "U".red(),
+      Delta::Untracked => "U".default(),
+    }
+  }
+}
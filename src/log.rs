@@ -5,19 +5,57 @@ use git2::*;
 use regex::Regex;
 use std::cmp::Ordering;
 use std::collections::binary_heap::BinaryHeap;
+use std::collections::{HashMap, HashSet};
 use std::path::*;
 
 pub struct LogArgs {
   pathspec: Option<Pathspec>,
+  pathspec_patterns: Vec<String>,
   all: bool,
   author: Option<Regex>,
   grep: Option<Regex>,
+  grep_trailers: Vec<TrailerFilter>,
   head: Option<String>,
   print_full: bool,
   print_patch: bool,
   print_list: bool,
+  name_status: bool,
+  print_stat: bool,
+  print_dirstat: bool,
+  diff_merges: DiffMerges,
+  submodule_diff: bool,
+  submodule_log: bool,
+  color_moved: bool,
+  word_diff: Option<WordDiffMode>,
+  root_paths: bool,
+  ignore_submodules: bool,
+  stat_width: usize,
+  // the width of the summary in the short format, None to pad to 50 without truncation
+  summary_width: Option<usize>,
+  // the width to wrap the message in the full format, None to keep the lines as they are
+  wrap_width: Option<usize>,
   num: Option<usize>,
   start: Option<usize>,
+  date: Option<DateMode>,
+  both_dates: bool,
+  deepen: Option<usize>,
+  invert_match: bool,
+  find_object: Option<String>,
+  decorate_repo: bool,
+  graph: bool,
+  show_email: bool,
+  show_tags: bool,
+  color_by_repo: bool,
+  decorate: DecorateMode,
+  since: Option<i64>,
+  since_as_filter: bool,
+  use_mailmap: bool,
+  json: bool,
+  json_lines: bool,
+  porcelain: bool,
+  group_by: Option<String>,
+  labels: LabelTheme,
+  separators: Separators,
 }
 
 impl LogArgs {
@@ -52,6 +90,106 @@ impl LogArgs {
           .long("grep")
           .help("Filter commits by commit message"),
       )
+      .arg(
+        clap::Arg::new("grep-trailer")
+          .long("grep-trailer")
+          .value_name("key[=regex]")
+          .action(ArgAction::Append)
+          .help("Filter commits having the trailer, e.g. `Signed-off-by`, whose value matches the regex if given; all of them must match when repeated"),
+      )
+      .arg(
+        clap::Arg::new("invert-match")
+          .long("invert-match")
+          .short('v')
+          .action(ArgAction::SetTrue)
+          .help("Show commits not matching --author and --grep, pathspec is not inverted"),
+      )
+      .arg(
+        clap::Arg::new("find-object")
+          .long("find-object")
+          .help("Filter commits adding or removing the object, abbreviated ids are accepted"),
+      )
+      .arg(
+        clap::Arg::new("ignore-submodules")
+          .long("ignore-submodules")
+          .value_parser(["none", "all"])
+          .help("With all, skip the submodules configured with `ignore = all`"),
+      )
+      .arg(
+        clap::Arg::new("decorate-repo")
+          .long("decorate-repo")
+          .action(ArgAction::SetTrue)
+          .help("Prefix each commit with the repo path relative to the root repo"),
+      )
+      .arg(
+        clap::Arg::new("graph")
+          .long("graph")
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all([
+            "author",
+            "grep",
+            "grep-trailer",
+            "find-object",
+            "pathspec",
+            "since-as-filter",
+            "start",
+            "full",
+            "list",
+            "name-status",
+            "patch",
+            "stat",
+            "patch-with-stat",
+            "dirstat",
+            "json",
+            "json-lines",
+            "format",
+            "group-by",
+          ])
+          .help("Draw the lanes of the commits in the short format, the repos never share a lane"),
+      )
+      .arg(
+        clap::Arg::new("show-email")
+          .long("show-email")
+          .action(ArgAction::SetTrue)
+          .help("Show the author email after the name in the short format"),
+      )
+      .arg(
+        clap::Arg::new("show-tags")
+          .long("show-tags")
+          .action(ArgAction::SetTrue)
+          .help("Show the nearest tag reachable from each commit like `git describe --tags`"),
+      )
+      .arg(
+        clap::Arg::new("color-by-repo")
+          .long("color-by-repo")
+          .action(ArgAction::SetTrue)
+          .help("Color the repo path of each commit, and its lanes with --graph, by a color per repo"),
+      )
+      .arg(
+        clap::Arg::new("decorate")
+          .long("decorate")
+          .num_args(0..=1)
+          .default_missing_value("short")
+          .value_parser(DecorateMode::VALUES)
+          .help("Show the ref names pointing at each commit"),
+      )
+      .arg(
+        clap::Arg::new("since")
+          .long("since")
+          .help("Show commits more recent than the date, stop at the first older commit"),
+      )
+      .arg(
+        clap::Arg::new("since-as-filter")
+          .long("since-as-filter")
+          .conflicts_with("since")
+          .help("Like --since but keep walking past older commits, slower but won't miss\nnewer commits behind them, e.g. with a skewed clock"),
+      )
+      .arg(
+        clap::Arg::new("use-mailmap")
+          .long("use-mailmap")
+          .action(ArgAction::SetTrue)
+          .help("Map author and committer names and emails by the .mailmap of each repo"),
+      )
       .arg(
         clap::Arg::new("list")
           .long("list")
@@ -59,6 +197,12 @@ impl LogArgs {
           .action(ArgAction::SetTrue)
           .help("List file of each commit"),
       )
+      .arg(
+        clap::Arg::new("name-status")
+          .long("name-status")
+          .action(ArgAction::SetTrue)
+          .help("List the status letter and the path of each file separated by tabs, like git"),
+      )
       .arg(
         clap::Arg::new("full")
           .long("full")
@@ -73,6 +217,85 @@ impl LogArgs {
           .action(ArgAction::SetTrue)
           .help("Show patch of each commit"),
       )
+      .arg(
+        clap::Arg::new("stat")
+          .long("stat")
+          .action(ArgAction::SetTrue)
+          .help("Show diffstat of each commit"),
+      )
+      .arg(
+        clap::Arg::new("patch-with-stat")
+          .long("patch-with-stat")
+          .action(ArgAction::SetTrue)
+          .help("Show diffstat followed by patch of each commit"),
+      )
+      .arg(
+        clap::Arg::new("dirstat")
+          .long("dirstat")
+          .action(ArgAction::SetTrue)
+          .help("Show the share of the changed lines under each directory of each commit"),
+      )
+      .arg(
+        clap::Arg::new("cc")
+          .long("cc")
+          .alias("combined")
+          .action(ArgAction::SetTrue)
+          .help("Diff merge commits against all parents, showing only what differs from every parent"),
+      )
+      .arg(
+        clap::Arg::new("diff-merges")
+          .long("diff-merges")
+          .value_name("mode")
+          .value_parser(DiffMerges::VALUES)
+          .conflicts_with("cc")
+          .help("How the changes of merge commits are shown: off leaves them out, first-parent diffs\nagainst the first parent (the default), combined is the same as --cc"),
+      )
+      .arg(
+        clap::Arg::new("no-submodule-diff")
+          .long("no-submodule-diff")
+          .action(ArgAction::SetTrue)
+          .help("Don't show the patch of submodule pointer changes"),
+      )
+      .arg(
+        clap::Arg::new("submodule")
+          .long("submodule")
+          .value_name("format")
+          .value_parser(["short", "log"])
+          .help("Show the commits between the old and new submodule pointers in patches with log"),
+      )
+      .arg(
+        clap::Arg::new("color-moved")
+          .long("color-moved")
+          .action(ArgAction::SetTrue)
+          .help("Show lines moved within a file in a different color"),
+      )
+      .arg(word_diff_arg())
+      .arg(
+        clap::Arg::new("relative-paths-in-patch")
+          .long("relative-paths-in-patch")
+          .action(ArgAction::SetTrue)
+          .help("Prefix the paths in patch headers with the submodule path"),
+      )
+      .arg(
+        clap::Arg::new("stat-width")
+          .long("stat-width")
+          .help("Set the width of the diffstat, default to the terminal width"),
+      )
+      .arg(
+        clap::Arg::new("max-width")
+          .long("max-width")
+          .value_name("n")
+          .help("Truncate the summaries to fit the short log in n columns, default to the terminal width when the output is a terminal"),
+      )
+      .arg(
+        clap::Arg::new("wrap")
+          .long("wrap")
+          .value_name("n")
+          .num_args(0..=1)
+          .default_missing_value("auto")
+          .requires("full")
+          .help("Wrap the long lines of the messages in the full view to n columns, or to the terminal width when the output is a terminal"),
+      )
       .arg(
         clap::Arg::new("num")
           .long("num")
@@ -87,28 +310,143 @@ impl LogArgs {
           .action(ArgAction::Set)
           .help("Set the number of log to start to displayed"),
       )
+      .arg(
+        clap::Arg::new("date")
+          .long("date")
+          .value_parser(DateMode::VALUES)
+          .help("Show dates in relative or absolute format"),
+      )
+      .arg(
+        clap::Arg::new("deepen")
+          .long("deepen")
+          .value_name("n")
+          .help("Fetch n more commits for shallow repos when their history ends, instead of warning"),
+      )
+      .arg(
+        clap::Arg::new("both-dates")
+          .long("both-dates")
+          .action(ArgAction::SetTrue)
+          .requires("full")
+          .conflicts_with("date")
+          .help("Show the absolute dates followed by the relative ones in the full view"),
+      )
+      .arg(
+        clap::Arg::new("json")
+          .long("json")
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all(["json-lines", "full", "list", "name-status", "patch", "stat", "patch-with-stat", "dirstat"])
+          .help("Print the commits as a json array"),
+      )
+      .arg(
+        clap::Arg::new("json-lines")
+          .long("json-lines")
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all(["full", "list", "name-status", "patch", "stat", "patch-with-stat", "dirstat"])
+          .help("Print each commit as a json object per line while walking"),
+      )
+      .arg(
+        clap::Arg::new("format")
+          .long("format")
+          .value_parser(["porcelain"])
+          .conflicts_with_all(["json", "json-lines", "full", "list", "name-status", "patch", "stat", "patch-with-stat", "dirstat"])
+          .help("Print the raw commit objects annotated with the repo path"),
+      )
+      .arg(
+        clap::Arg::new("group-by")
+          .long("group-by")
+          .value_parser(["author", "repo"])
+          .conflicts_with_all(["json-lines", "format", "full", "list", "name-status", "patch", "stat", "patch-with-stat", "dirstat"])
+          .help("Print the number of matched commits per author or repo instead, works with --json"),
+      )
+      .arg(minimal_arg())
+      .arg(crlf_arg())
+      .args(label_args())
+      .args(separator_args())
   }
 }
 
 impl From<&clap::ArgMatches> for LogArgs {
   fn from(matches: &clap::ArgMatches) -> LogArgs {
     let author_pattern = matches
-      .get_one::<&str>("author")
+      .get_one::<String>("author")
       .map(|s| Regex::new(s).unwrap_or_else(|_| err_exit!("Crate regex for author failed")));
     let grep_pattern = matches
-      .get_one::<&str>("grep")
+      .get_one::<String>("grep")
       .map(|s| Regex::new(s).unwrap_or_else(|_| err_exit!("Crate regex for grep failed")));
     return LogArgs {
-      pathspec: matches
+      pathspec: matches.get_many::<String>("pathspec").map(|s| {
+        Pathspec::new(s.map(|p| anchor_pathspec(p)))
+          .unwrap_or_else(|_| err_exit!("Crate pathspec failed"))
+      }),
+      pathspec_patterns: matches
         .get_many::<String>("pathspec")
-        .map(|s| Pathspec::new(s).unwrap_or_else(|_| err_exit!("Crate pathspec failed"))),
+        .map(|s| s.map(|p| anchor_pathspec(p)).collect())
+        .unwrap_or_default(),
       all: matches.get_flag("all"),
       author: author_pattern,
       grep: grep_pattern,
+      grep_trailers: matches
+        .get_many::<String>("grep-trailer")
+        .into_iter()
+        .flatten()
+        .map(|s| TrailerFilter::from(s))
+        .collect(),
       head: matches.get_one::<String>("revision").map(|s| s.clone()),
       print_full: matches.get_flag("full"),
-      print_patch: matches.get_flag("patch"),
+      print_patch: matches.get_flag("patch") || matches.get_flag("patch-with-stat"),
       print_list: matches.get_flag("list"),
+      name_status: matches.get_flag("name-status"),
+      diff_merges: match matches.get_one::<String>("diff-merges") {
+        Some(s) => DiffMerges::from(s),
+        None if matches.get_flag("cc") => DiffMerges::Combined,
+        None => DiffMerges::FirstParent,
+      },
+      print_stat: matches.get_flag("stat") || matches.get_flag("patch-with-stat"),
+      print_dirstat: matches.get_flag("dirstat"),
+      submodule_diff: !matches.get_flag("no-submodule-diff"),
+      submodule_log: matches.get_one::<String>("submodule").map(String::as_str) == Some("log"),
+      color_moved: matches.get_flag("color-moved"),
+      word_diff: parse_word_diff(matches),
+      root_paths: matches.get_flag("relative-paths-in-patch"),
+      ignore_submodules: matches
+        .get_one::<String>("ignore-submodules")
+        .map(String::as_str)
+        == Some("all"),
+      stat_width: parse_stat_width(matches),
+      summary_width: parse_summary_width(matches),
+      wrap_width: parse_wrap_width(matches),
+      invert_match: matches.get_flag("invert-match"),
+      find_object: matches.get_one::<String>("find-object").map(|s| {
+        if s.is_empty() || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+          err_exit!("Invalid object id: {}", s);
+        }
+        s.to_ascii_lowercase()
+      }),
+      decorate_repo: matches.get_flag("decorate-repo"),
+      graph: matches.get_flag("graph"),
+      show_email: matches.get_flag("show-email"),
+      show_tags: matches.get_flag("show-tags"),
+      color_by_repo: matches.get_flag("color-by-repo"),
+      decorate: matches
+        .get_one::<String>("decorate")
+        .map(|s| DecorateMode::from(s))
+        .unwrap_or(DecorateMode::No),
+      since: matches
+        .get_one::<String>("since")
+        .or(matches.get_one::<String>("since-as-filter"))
+        .map(|s| {
+          parse_date(s, Local::now())
+            .unwrap_or_else(|| err_exit!("Can't parse the date: {}", s))
+            .timestamp()
+        }),
+      since_as_filter: matches.contains_id("since-as-filter"),
+      use_mailmap: matches.get_flag("use-mailmap"),
+      json: matches.get_flag("json"),
+      json_lines: matches.get_flag("json-lines"),
+      porcelain: matches.get_one::<String>("format").is_some(),
+      group_by: matches.get_one::<String>("group-by").cloned(),
+      labels: LabelTheme::from(matches),
+      separators: Separators::from(matches),
       num: matches.get_one::<String>("num").map(|s| {
         s.parse::<usize>()
           .unwrap_or_else(|e| err_exit!("Error while parsing -n option: {}", e))
@@ -117,10 +455,102 @@ impl From<&clap::ArgMatches> for LogArgs {
         s.parse::<usize>()
           .unwrap_or_else(|e| err_exit!("Error while parsing -s option: {}", e))
       }),
+      date: matches.get_one::<String>("date").map(|s| DateMode::from(s)),
+      both_dates: matches.get_flag("both-dates"),
+      deepen: matches.get_one::<String>("deepen").map(|s| {
+        s.parse::<usize>()
+          .unwrap_or_else(|e| err_exit!("Error while parsing --deepen option: {}", e))
+      }),
     };
   }
 }
 
+// the columns of the short log besides the summary: the id, the separator, and room for
+// the date, the author and the repo
+const SHORT_RESERVED_WIDTH: usize = 50;
+const MIN_SUMMARY_WIDTH: usize = 20;
+
+// fit the summary to the line width, the piped output keeps the fixed width to stay stable
+fn parse_summary_width(matches: &clap::ArgMatches) -> Option<usize> {
+  let width = match matches.get_one::<String>("max-width") {
+    Some(s) => Some(
+      s.parse::<usize>()
+        .unwrap_or_else(|e| err_exit!("Error while parsing --max-width option: {}", e)),
+    ),
+    None if atty::is(atty::Stream::Stdout) => {
+      terminal_size::terminal_size().map(|(terminal_size::Width(w), _)| w as usize)
+    }
+    None => None,
+  };
+  width.map(|w| {
+    w.saturating_sub(SHORT_RESERVED_WIDTH)
+      .max(MIN_SUMMARY_WIDTH)
+  })
+}
+
+// pad the summary to the width so the following columns line up, cut it if it's longer
+// the width is counted in terminal columns, so the wide CJK and emoji summaries line up too
+fn fit_summary(summary: &str, width: Option<usize>) -> String {
+  let width = match width {
+    Some(w) => w,
+    None => return pad_to_width(summary, 50),
+  };
+  if text_width(summary) <= width {
+    return pad_to_width(summary, width);
+  }
+  // a wide char which doesn't fit is replaced by the padding
+  format!(
+    "{}...",
+    pad_to_width(take_width(summary, width - 3), width - 3)
+  )
+}
+
+// the message is indented by 4 columns in the full format
+const MESSAGE_INDENT: usize = 4;
+
+// `--wrap` alone follows the terminal, and keeps the piped output unwrapped
+fn parse_wrap_width(matches: &clap::ArgMatches) -> Option<usize> {
+  let width = match matches.get_one::<String>("wrap").map(String::as_str) {
+    Some("auto") if atty::is(atty::Stream::Stdout) => {
+      terminal_size::terminal_size().map(|(terminal_size::Width(w), _)| w as usize)
+    }
+    Some("auto") | None => None,
+    Some(s) => Some(
+      s.parse::<usize>()
+        .unwrap_or_else(|e| err_exit!("Error while parsing --wrap option: {}", e)),
+    ),
+  };
+  width.map(|w| w.saturating_sub(MESSAGE_INDENT).max(MIN_SUMMARY_WIDTH))
+}
+
+// break the long lines of the message at the spaces to fit the width
+// the hard line breaks are kept, and the indented lines like code blocks are left as they are
+fn wrap_message(message: &str, width: usize) -> String {
+  let mut lines = Vec::new();
+  for line in message.lines() {
+    if line.chars().count() <= width || line.starts_with(char::is_whitespace) {
+      lines.push(line.to_string());
+      continue;
+    }
+    let mut current = String::new();
+    for word in line.split(' ').filter(|w| !w.is_empty()) {
+      if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+        lines.push(std::mem::take(&mut current));
+      }
+      if !current.is_empty() {
+        current.push(' ');
+      }
+      current.push_str(word);
+    }
+    lines.push(current);
+  }
+  let mut wrapped = lines.join("\n");
+  if message.ends_with('\n') {
+    wrapped.push('\n');
+  }
+  wrapped
+}
+
 struct CommitWrapper<'a> {
   c: Commit<'a>,
   t: Time,
@@ -150,67 +580,163 @@ impl<'a> CommitWrapper<'a> {
 impl<'a> Eq for CommitWrapper<'a> {}
 impl<'a> PartialEq for CommitWrapper<'a> {
   fn eq(&self, other: &CommitWrapper) -> bool {
-    return self.t.eq(&other.t);
+    return self.cmp(other) == Ordering::Equal;
   }
 }
 
+// newer commits first, commits of the same time are ordered by repo path then id
+// so the output is stable across runs
 impl<'a> Ord for CommitWrapper<'a> {
   fn cmp(&self, other: &CommitWrapper) -> Ordering {
-    return self.t.cmp(&other.t);
+    return self
+      .t
+      .cmp(&other.t)
+      .then_with(|| other.p.cmp(self.p))
+      .then_with(|| other.c.id().cmp(&self.c.id()));
   }
 }
 
 impl<'a> PartialOrd for CommitWrapper<'a> {
   fn partial_cmp(&self, other: &CommitWrapper) -> Option<Ordering> {
-    return self.t.partial_cmp(&other.t);
+    return Some(self.cmp(other));
   }
 }
 
 struct CommitsWalker<'a> {
   heads: BinaryHeap<CommitWrapper<'a>>,
+  deepen: Option<usize>,
+  // the repos which have been deepened or warned about, by their working path
+  shallow_repos: HashSet<&'a Path>,
+  // the ids of the commits already walked in each repo, a commit reachable from several heads
+  // or parents is shown once even if the copies aren't next to each other in the heap
+  // the ids of a repo are dropped once none of its commits is left in the heap
+  seen: HashMap<&'a Path, HashSet<Oid>>,
+  // the number of commits of each repo in the heap
+  pending: HashMap<&'a Path, usize>,
 }
 
 impl<'a> CommitsWalker<'a> {
-  pub fn new(heads: Vec<CommitWrapper<'a>>) -> CommitsWalker<'a> {
-    let heap = BinaryHeap::from_iter(heads.into_iter());
-    return Self { heads: heap };
+  pub fn new(heads: Vec<CommitWrapper<'a>>, deepen: Option<usize>) -> CommitsWalker<'a> {
+    let mut walker = Self {
+      heads: BinaryHeap::new(),
+      deepen,
+      shallow_repos: HashSet::new(),
+      seen: HashMap::new(),
+      pending: HashMap::new(),
+    };
+    heads.into_iter().for_each(|c| walker.push(c));
+    return walker;
+  }
+
+  fn push(&mut self, commit: CommitWrapper<'a>) {
+    *self.pending.entry(commit.p).or_default() += 1;
+    self.heads.push(commit);
+  }
+
+  fn pop(&mut self) -> Option<CommitWrapper<'a>> {
+    let commit = self.heads.pop()?;
+    if let Some(n) = self.pending.get_mut(commit.p) {
+      *n -= 1;
+    }
+    Some(commit)
+  }
+
+  // the commits of a repo only come back to the heap as parents of its commits in the heap,
+  // so without any the repo is walked through and its ids are no longer needed
+  fn forget_if_done(&mut self, path: &'a Path) {
+    if self.pending.get(path) == Some(&0) {
+      self.pending.remove(path);
+      self.seen.remove(path);
+    }
+  }
+
+  // the parent is missing at the boundary of a shallow clone
+  // fetch more history once per repo if --deepen is given, otherwise warn about the truncation
+  fn shallow_parent(&mut self, commit: &CommitWrapper<'a>, id: Oid) -> Option<Commit<'a>> {
+    if !commit.r.is_shallow() || !self.shallow_repos.insert(commit.p) {
+      return None;
+    }
+    let path = commit.p.display();
+    match self.deepen {
+      Some(depth) => {
+        eprintln!("Deepening {} by {} commits", path, depth);
+        let status = std::process::Command::new("git")
+          .arg("fetch")
+          .arg(format!("--deepen={}", depth))
+          .current_dir(commit.p)
+          .status();
+        if !matches!(status, Ok(s) if s.success()) {
+          warn_or_exit!("Warning: deepen {} failed", path);
+        }
+        commit.r.find_commit(id).ok()
+      }
+      None => {
+        warn_or_exit!(
+          "Warning: the history of {} is truncated at the shallow commit {}, use --deepen to fetch more",
+          path,
+          &commit.c.id().to_string()[..7]
+        );
+        None
+      }
+    }
   }
 }
 
 impl<'a> std::iter::Iterator for CommitsWalker<'a> {
   type Item = CommitWrapper<'a>;
   fn next(&mut self) -> Option<Self::Item> {
-    let latest = match self.heads.pop() {
-      Some(c) => c,
-      None => return None,
+    let latest = loop {
+      let c = self.pop()?;
+      if self.seen.entry(c.p).or_default().insert(c.c.id()) {
+        break c;
+      }
+      self.forget_if_done(c.p);
     };
-    loop {
-      if let Some(c) = self.heads.peek() {
-        if *c == latest {
-          self.heads.pop();
-          continue;
-        }
+    for id in latest.c.parent_ids() {
+      if self.seen.get(latest.p).is_some_and(|s| s.contains(&id)) {
+        continue;
+      }
+      let parent = match latest.r.find_commit(id) {
+        Ok(c) => Some(c),
+        Err(_) => self.shallow_parent(&latest, id),
+      };
+      if let Some(c) = parent {
+        self.push(CommitWrapper::new(c, latest.p, latest.r));
       }
-      break;
     }
-    latest
-      .c
-      .parents()
-      .for_each(|c| self.heads.push(CommitWrapper::new(c, latest.p, latest.r)));
+    self.forget_if_done(latest.p);
     return Some(latest);
   }
 }
 
-fn collect_submodules(repo: Repository) -> Vec<Repository> {
-  let subs = repo.submodules().expect("Get submodule failed");
+// `skip_ignored` leaves out the submodules configured with `ignore = all`
+fn collect_submodules(
+  repo: Repository,
+  skip_ignored: bool,
+) -> Result<Vec<Repository>, GitSubError> {
+  let subs = repo.submodules()?;
   let mut repos = Vec::new();
-  subs
-    .iter()
-    .map(|s| s.open().expect("Open submodules failed"))
-    .for_each(|r| repos.extend(collect_submodules(r)));
+  for s in subs.iter() {
+    if skip_ignored && s.ignore_rule() == SubmoduleIgnore::All {
+      continue;
+    }
+    match s.open() {
+      Ok(r) => repos.extend(collect_submodules(r, skip_ignored)?),
+      Err(e) if is_strict() => {
+        return Err(GitSubError::SubmoduleOpen(
+          submodule_display_path(&repo, s),
+          e,
+        ))
+      }
+      Err(e) => {
+        let path = submodule_display_path(&repo, s);
+        eprintln!("Open submodule {} failed: {}", path, e.message());
+      }
+    }
+  }
   drop(subs);
   repos.push(repo);
-  return repos;
+  Ok(repos)
 }
 
 fn collect_submodule_heads_with_rev<'a>(
@@ -218,147 +744,218 @@ fn collect_submodule_heads_with_rev<'a>(
   repo: &Repository,
   heads: &'a mut Vec<Oid>,
   sub_mods: &'a mut Vec<Repository>,
-) {
-  rev
-    .tree()
-    .expect("Get tree failed")
-    .walk(TreeWalkMode::PreOrder, |_, e| -> TreeWalkResult {
+  skip_ignored: bool,
+) -> Result<(), GitSubError> {
+  // the walk can only be aborted from the callback, the error is kept for after it
+  let mut failed = None;
+  let walked = rev
+    .tree()?
+    .walk(TreeWalkMode::PreOrder, |root, e| -> TreeWalkResult {
       if e.kind() != Some(ObjectType::Commit) {
         return TreeWalkResult::Ok;
       }
-      let sub = repo
-        .find_submodule(e.name().expect("Get object name failed"))
-        .expect("Find submodule failed")
-        .open()
-        .expect("Open submodule failed");
-      let sub_head = sub
-        .find_commit(e.id())
-        .expect("Can't find commit in the submodule");
+      // the submodule is looked up by its full path in the tree
+      let name = format!("{}{}", root, e.name().unwrap_or_default());
+      let sub = match repo.find_submodule(&name) {
+        Ok(s) => s,
+        Err(err) if is_strict() => {
+          failed = Some(GitSubError::SubmoduleOpen(name, err));
+          return TreeWalkResult::Abort;
+        }
+        Err(err) => {
+          eprintln!("Find submodule {} failed: {}", name, err.message());
+          return TreeWalkResult::Ok;
+        }
+      };
+      if skip_ignored && sub.ignore_rule() == SubmoduleIgnore::All {
+        return TreeWalkResult::Ok;
+      }
+      let path = submodule_display_path(repo, &sub);
+      let sub = match sub.open() {
+        Ok(r) => r,
+        Err(err) if is_strict() => {
+          failed = Some(GitSubError::SubmoduleOpen(path, err));
+          return TreeWalkResult::Abort;
+        }
+        Err(err) => {
+          eprintln!("Open submodule {} failed: {}", path, err.message());
+          return TreeWalkResult::Ok;
+        }
+      };
+      let sub_head = match sub.find_commit(e.id()) {
+        Ok(c) => c,
+        Err(err) if is_strict() => {
+          failed = Some(err.into());
+          return TreeWalkResult::Abort;
+        }
+        Err(err) => {
+          eprintln!(
+            "Can't find commit {} in submodule {}: {}",
+            e.id(),
+            path,
+            err.message()
+          );
+          return TreeWalkResult::Ok;
+        }
+      };
+      // nested submodules go first, keeping the heads in the same order as the repos
+      if let Err(err) =
+        collect_submodule_heads_with_rev(&sub_head, &sub, heads, sub_mods, skip_ignored)
+      {
+        failed = Some(err);
+        return TreeWalkResult::Abort;
+      }
       heads.push(sub_head.id());
-      collect_submodule_heads_with_rev(&sub_head, &sub, heads, sub_mods);
       drop(sub_head);
       sub_mods.push(sub);
-      return TreeWalkResult::Ok;
-    })
-    .expect("Walk tree failed");
+      TreeWalkResult::Ok
+    });
+  match failed {
+    Some(e) => Err(e),
+    None => Ok(walked?),
+  }
 }
 
 fn collect_heads<'a>(
   repos: &'a Vec<Repository>,
   args: &LogArgs,
   heads: &mut Vec<CommitWrapper<'a>>,
-) {
-  repos.iter().for_each(|r| {
-    let repo_path = r.workdir().unwrap();
+) -> Result<(), GitSubError> {
+  for r in repos.iter() {
+    let repo_path = r.workdir().expect("Get workdir failed");
     if args.all {
-      r.branches(None)
-        .expect("Get branches failed")
-        .for_each(|b| {
-          let commit = b
-            .expect("Get branch failed")
-            .0
-            .get()
-            .peel_to_commit()
-            .expect("get commit failed");
-          heads.push(CommitWrapper::new(commit, repo_path, r));
-        })
+      for b in r.branches(None)? {
+        let commit = b?.0.get().peel_to_commit()?;
+        heads.push(CommitWrapper::new(commit, repo_path, r));
+      }
     } else {
-      let commit = r
-        .head()
-        .expect("Get head failed")
-        .peel_to_commit()
-        .expect("get commit failed");
-      heads.push(CommitWrapper::new(commit, repo_path, r));
+      // a freshly initialized repo has no commits to show yet
+      let head = match r.head() {
+        Ok(h) => h,
+        Err(e) if e.code() == ErrorCode::UnbornBranch => continue,
+        Err(e) => return Err(e.into()),
+      };
+      heads.push(CommitWrapper::new(head.peel_to_commit()?, repo_path, r));
     }
-  });
-}
-
-fn format_duration(dur: chrono::Duration) -> String {
-  if dur.num_days() > 30 {
-    format!("{} months ago", dur.num_days() / 30)
-  } else if dur.num_days() > 0 {
-    format!("{} days ago", dur.num_days())
-  } else if dur.num_hours() > 0 {
-    format!("{} hours ago", dur.num_hours())
-  } else if dur.num_minutes() > 0 {
-    format!("{} mins ago", dur.num_minutes())
-  } else if dur.num_seconds() > 0 {
-    format!("{} secs ago", dur.num_seconds())
-  } else {
-    String::from("just now")
   }
+  Ok(())
 }
 
-fn print_commit(commit: CommitWrapper, base_path: &Path, now: DateTime<Local>, args: &LogArgs) {
-  let committer_time = Local.timestamp(commit.t.seconds(), 0);
-  let duration = format_duration(now - committer_time);
+// the author and committer of the commit, resolved by the mailmap if given
+fn commit_idents(
+  commit: &Commit,
+  mailmap: Option<&Mailmap>,
+) -> (Signature<'static>, Signature<'static>) {
+  let resolve = |sig: Signature| match mailmap {
+    Some(m) => m.resolve_signature(&sig).unwrap_or_else(|_| sig.to_owned()),
+    None => sig.to_owned(),
+  };
+  (resolve(commit.author()), resolve(commit.committer()))
+}
+
+// what print_commit needs besides the commit, built once for the whole log
+struct PrintContext<'a> {
+  base_path: &'a Path,
+  root_path: &'a Path,
+  now: DateTime<Local>,
+  // each repo has its own .mailmap
+  mailmaps: HashMap<&'a Path, Mailmap>,
+  // the ref names of each repo, looked up by the commit id
+  decorations: HashMap<&'a Path, Decorations>,
+  colors: Option<RepoColors>,
+  // describe is repo-local, so each repo has its own describer
+  describers: HashMap<&'a Path, TagDescriber>,
+  args: &'a LogArgs,
+}
+
+fn print_commit(commit: CommitWrapper, ctx: &PrintContext) {
+  let args = ctx.args;
+  let (base_path, now) = (ctx.base_path, ctx.now);
+  let (author, committer) = commit_idents(&commit.c, ctx.mailmaps.get(commit.p));
   let path = commit
     .p
     .canonicalize()
     .expect("Get canonicalize path failed");
+  if args.decorate_repo {
+    // the repo path relative to the root repo
+    let repo_str = relative_path(commit.p, ctx.root_path);
+    print!("{} ", format!("[{}]", repo_str).purple());
+  }
   if args.print_full {
-    let author_time = Local.timestamp(commit.c.author().when().seconds(), 0);
-    if path == base_path {
-      println!(
-        "{} - {}",
-        commit.c.id().to_string().yellow(),
-        commit.p.display().to_string().bright_blue()
-      );
-    } else {
-      println!(
-        "{} - {}",
-        commit.c.id().to_string().yellow(),
-        path
-          .strip_prefix(base_path)
-          .unwrap_or(&path)
-          .display()
-          .to_string()
-          .bright_blue()
-      );
+    let date_mode = args.date.unwrap_or(DateMode::Absolute);
+    let date_str = |time: Time| {
+      if args.both_dates {
+        format!(
+          "{} ({})",
+          format_time(time, DateMode::Absolute, now),
+          format_time(time, DateMode::Relative, now)
+        )
+      } else {
+        format_time(time, date_mode, now)
+      }
+    };
+    print!("{}", commit.c.id().to_string().yellow());
+    if let Some(d) = ctx.decorations.get(commit.p) {
+      d.print(commit.c.id());
     }
-    println!("Author:     {}", commit.c.author());
-    println!("AuthorDate: {}", author_time.format("%a %b %d %T %Y %z"));
-    println!("Commit:     {}", commit.c.committer());
-    println!("CommitDate: {}", committer_time.format("%a %b %d %T %Y %z"));
-    println!(
-      "\n    {}",
-      commit.c.message().unwrap_or("").replace("\n", "\n    ")
-    );
+    if let Some(d) = ctx.describers.get(commit.p) {
+      d.print(&commit.c);
+    }
+    println!(" - {}", relative_path(&path, base_path).bright_blue());
+    println!("Author:     {}", author);
+    println!("AuthorDate: {}", date_str(commit.c.author().when()));
+    println!("Commit:     {}", committer);
+    println!("CommitDate: {}", date_str(commit.t));
+    let message = commit.c.message().unwrap_or("");
+    let message = match args.wrap_width {
+      Some(w) => wrap_message(message, w),
+      None => message.to_string(),
+    };
+    println!("\n    {}", message.replace("\n", "\n    "));
   } else {
-    if path == base_path {
-      println!(
-        "{} - {:50} ({}) <{}> ({})",
-        &commit.c.id().to_string()[..7].red(),
-        commit.c.summary().unwrap_or_default(),
-        duration.green(),
-        commit
-          .c
-          .author()
-          .name()
-          .unwrap_or("!!NO NAME!!")
-          .to_string()
-          .bright_blue(),
-        commit.p.display(),
-      )
-    } else {
-      println!(
-        "{} - {:50} ({}) <{}> (./{})",
-        &commit.c.id().to_string()[..7].red(),
-        commit.c.summary().unwrap_or_default(),
-        duration.green(),
-        commit
-          .c
-          .author()
-          .name()
-          .unwrap_or("!!NO NAME!!")
-          .to_string()
-          .bright_blue(),
-        path.strip_prefix(base_path).unwrap_or(&path).display(),
-      );
+    let duration = format_time(commit.t, args.date.unwrap_or(DateMode::Relative), now);
+    print!(
+      "{}",
+      commit.c.id().to_string()[..7].paint(color_for("log.hash"))
+    );
+    if let Some(d) = ctx.decorations.get(commit.p) {
+      d.print(commit.c.id());
     }
+    if let Some(d) = ctx.describers.get(commit.p) {
+      d.print(&commit.c);
+    }
+    let repo_str = relative_path(&path, base_path);
+    print!(
+      " - {} ({}) <{}>",
+      fit_summary(commit.c.summary().unwrap_or_default(), args.summary_width),
+      duration.paint(color_for("log.date")),
+      author.name().unwrap_or("!!NO NAME!!").bright_blue(),
+    );
+    if args.show_email {
+      let email = author.email().filter(|e| !e.is_empty());
+      print!(" <{}>", email.unwrap_or("no-email").bright_blue());
+    }
+    println!(
+      " ({})",
+      match ctx.colors {
+        Some(ref c) => c.paint(commit.p, &repo_str),
+        None => repo_str.default(),
+      },
+    );
   }
-  if args.print_list || args.print_patch {
-    let diff = commit
+  // the combined diff replaces the list and patch against the first parent
+  let is_merge = commit.c.parent_count() > 1;
+  let combined = is_merge && args.diff_merges == DiffMerges::Combined;
+  if is_merge && args.diff_merges == DiffMerges::Off {
+    return;
+  }
+  if args.print_list
+    || args.name_status
+    || args.print_patch
+    || args.print_stat
+    || args.print_dirstat
+  {
+    let mut diff = commit
       .r
       .diff_tree_to_tree(
         commit
@@ -372,21 +969,22 @@ fn print_commit(commit: CommitWrapper, base_path: &Path, now: DateTime<Local>, a
         Some(&mut DiffOptions::default()),
       )
       .expect("Get diff from parent failed");
+    super::status::find_renames(commit.r, &mut diff);
+    if args.print_stat || args.print_dirstat {
+      let mut stat = ChangeStat::new();
+      diff.deltas().for_each(|d| {
+        super::status::add_stat(&mut stat, commit.r, &d, delta_to_status(d.status()));
+      });
+      if args.print_dirstat {
+        stat.print_dirstat();
+      }
+      if args.print_stat {
+        stat.print(args.stat_width);
+      }
+    }
     diff.deltas().for_each(|d| {
-      if args.print_list {
-        let label = match d.status() {
-          Delta::Added => "A".green(),
-          Delta::Conflicted => "C".red(),
-          Delta::Copied => "C".green(),
-          Delta::Deleted => "D".red(),
-          Delta::Ignored => "I".red(),
-          Delta::Modified => "M".red(),
-          Delta::Renamed => "R".green(),
-          Delta::Typechange => "T".green(),
-          Delta::Unmodified => "U".green(),
-          Delta::Unreadable => "U".red(),
-          Delta::Untracked => "U".default(),
-        };
+      if args.print_list && !combined {
+        let label = args.labels.delta_label(d.status());
         if d.status() == Delta::Renamed {
           let old_name = d.old_file().path().expect("Get old file name failed");
           let new_name = d.new_file().path().expect("Get old file name failed");
@@ -402,109 +1000,456 @@ fn print_commit(commit: CommitWrapper, base_path: &Path, now: DateTime<Local>, a
         }
       }
 
-      if args.print_patch {
-        let status = match d.status() {
-          Delta::Added => Status::INDEX_NEW,
-          Delta::Conflicted => Status::CONFLICTED,
-          Delta::Copied => Status::INDEX_NEW,
-          Delta::Deleted => Status::INDEX_DELETED,
-          Delta::Ignored => Status::IGNORED,
-          Delta::Modified => Status::INDEX_MODIFIED,
-          Delta::Renamed => Status::INDEX_RENAMED,
-          Delta::Typechange => Status::INDEX_TYPECHANGE,
-          Delta::Unmodified => Status::CURRENT,
-          Delta::Unreadable => Status::IGNORED,
-          Delta::Untracked => Status::IGNORED,
+      if args.name_status && !combined {
+        let old_name = d.old_file().path().expect("Get old file name failed");
+        let new_name = d.new_file().path().expect("Get new file name failed");
+        let sep = args.separators.field("\t");
+        if d.status() == Delta::Renamed || d.status() == Delta::Copied {
+          print!(
+            "{}{}{}{}{}{}",
+            name_status_letter(d.status()),
+            sep,
+            display_path(old_name),
+            sep,
+            display_path(new_name),
+            args.separators.end()
+          );
+        } else {
+          print!(
+            "{}{}{}{}",
+            name_status_letter(d.status()),
+            sep,
+            display_path(new_name),
+            args.separators.end()
+          );
+        }
+      }
+
+      if args.print_patch && !combined && (args.submodule_diff || !super::status::is_git_link(&d)) {
+        let prefix = if args.root_paths {
+          super::status::patch_prefix(commit.p, ctx.root_path)
+        } else {
+          String::new()
         };
-        super::status::print_patch(commit.r, &d, status);
+        super::status::print_patch(
+          commit.r,
+          &d,
+          delta_to_status(d.status()),
+          args.color_moved,
+          args.word_diff,
+          args.submodule_log,
+          &prefix,
+        );
       }
     })
   }
+  if combined && args.print_list {
+    for (kind, path) in combined_changes(commit.r, &commit.c) {
+      println!("  {} {}", args.labels.delta_label(kind), path.display());
+    }
+  }
+  if combined && args.name_status {
+    for (kind, path) in combined_changes(commit.r, &commit.c) {
+      print!(
+        "{}{}{}{}",
+        name_status_letter(kind),
+        args.separators.field("\t"),
+        display_path(&path),
+        args.separators.end()
+      );
+    }
+  }
+  if combined && args.print_patch {
+    let prefix = if args.root_paths {
+      super::status::patch_prefix(commit.p, ctx.root_path)
+    } else {
+      String::new()
+    };
+    print_combined_diff(commit.r, &commit.c, &prefix);
+  }
+}
+
+// the path of the repo of the commit for machine readable output
+fn commit_repo_str(commit: &CommitWrapper, base_path: &Path) -> String {
+  let path = commit
+    .p
+    .canonicalize()
+    .expect("Get canonicalize path failed");
+  relative_path(&path, base_path)
+}
+
+// the commit as a json object, in one line unless pretty
+fn commit_json(
+  commit: &CommitWrapper,
+  base_path: &Path,
+  mailmap: Option<&Mailmap>,
+  pretty: bool,
+) -> String {
+  let (author, committer) = commit_idents(&commit.c, mailmap);
+  // the trailers by key in the order they first show up, a key may be given several times
+  let mut trailers: Vec<(String, Vec<String>)> = Vec::new();
+  for (key, value) in parse_trailers(commit.c.message().unwrap_or("")) {
+    match trailers.iter_mut().find(|(k, _)| *k == key) {
+      Some((_, values)) => values.push(value),
+      None => trailers.push((key, vec![value])),
+    }
+  }
+  let trailer_fields: Vec<(&str, String)> = trailers
+    .iter()
+    .map(|(k, values)| {
+      let values: Vec<String> = values.iter().map(|v| json::json_str(v)).collect();
+      (k.as_str(), format!("[{}]", values.join(",")))
+    })
+    .collect();
+  let fields = [
+    ("repo", json::json_str(&commit_repo_str(commit, base_path))),
+    ("id", json::json_str(&commit.c.id().to_string())),
+    (
+      "author_name",
+      json::json_str(author.name().unwrap_or_default()),
+    ),
+    (
+      "author_email",
+      json::json_str(author.email().unwrap_or_default()),
+    ),
+    (
+      "author_date",
+      json::json_str(&format_iso(commit.c.author().when())),
+    ),
+    (
+      "committer_name",
+      json::json_str(committer.name().unwrap_or_default()),
+    ),
+    (
+      "committer_email",
+      json::json_str(committer.email().unwrap_or_default()),
+    ),
+    ("commit_date", json::json_str(&format_iso(commit.t))),
+    (
+      "summary",
+      json::json_str(commit.c.summary().unwrap_or_default()),
+    ),
+    (
+      "message",
+      json::json_str(commit.c.message().unwrap_or_default()),
+    ),
+    ("trailers", json::json_object(&trailer_fields, false, "")),
+  ];
+  json::json_object(&fields, pretty, if pretty { "  " } else { "" })
+}
+
+// print the commit object as is like `git log --format=raw`, with the repo path after the id
+// the message is indented so a line starting with "commit " always begins a new commit
+fn print_commit_porcelain(commit: &CommitWrapper, base_path: &Path) {
+  println!("commit {}", commit.c.id());
+  println!("repo {}", commit_repo_str(commit, base_path));
+  print!("{}", String::from_utf8_lossy(commit.c.raw_header_bytes()));
+  println!();
+  for line in String::from_utf8_lossy(commit.c.message_raw_bytes()).lines() {
+    println!("    {}", line);
+  }
+  println!();
 }
 
+// the status letter of `git log --name-status`, unlike the labels it can't be customized
+fn name_status_letter(delta: Delta) -> ColoredString {
+  match delta {
+    Delta::Added => "A".green(),
+    Delta::Copied => "C".green(),
+    Delta::Deleted => "D".red(),
+    Delta::Modified => "M".red(),
+    Delta::Renamed => "R".green(),
+    Delta::Typechange => "T".green(),
+    Delta::Conflicted => "U".red(),
+    _ => "X".red(),
+  }
+}
+
+// map the delta of commit diff to the status of staged change
+fn delta_to_status(delta: Delta) -> Status {
+  match delta {
+    Delta::Added => Status::INDEX_NEW,
+    Delta::Conflicted => Status::CONFLICTED,
+    Delta::Copied => Status::INDEX_NEW,
+    Delta::Deleted => Status::INDEX_DELETED,
+    Delta::Ignored => Status::IGNORED,
+    Delta::Modified => Status::INDEX_MODIFIED,
+    Delta::Renamed => Status::INDEX_RENAMED,
+    Delta::Typechange => Status::INDEX_TYPECHANGE,
+    Delta::Unmodified => Status::CURRENT,
+    Delta::Unreadable => Status::IGNORED,
+    Delta::Untracked => Status::IGNORED,
+  }
+}
+
+// the pathspec rewritten relative to a repo, to test its trees before diffing the commits
+enum TreePathspec {
+  // can't tell from the patterns, every commit has to be diffed
+  All,
+  // no path of the repo can match
+  Nothing,
+  Tree(Pathspec),
+}
+
+impl TreePathspec {
+  // `prefix` is the path of the repo relative to the root repo
+  fn new(patterns: &[String], prefix: &Path) -> TreePathspec {
+    if prefix.as_os_str().is_empty() {
+      return match Pathspec::new(patterns) {
+        Ok(p) => TreePathspec::Tree(p),
+        Err(_) => TreePathspec::All,
+      };
+    }
+    let prefix = prefix.display().to_string().replace("\\", "/");
+    let mut rewritten = Vec::new();
+    for pattern in patterns {
+      let pattern = pattern.trim_end_matches('/');
+      // globs and magic may match anything, leave them to the diff
+      if pattern.is_empty() || pattern.starts_with([':', '!']) || pattern.contains(['*', '?', '['])
+      {
+        return TreePathspec::All;
+      }
+      if pattern == prefix || prefix.starts_with(&format!("{}/", pattern)) {
+        // the whole repo is under the pattern
+        return TreePathspec::All;
+      }
+      if let Some(p) = pattern.strip_prefix(&format!("{}/", prefix)) {
+        rewritten.push(String::from(p));
+      }
+    }
+    if rewritten.is_empty() {
+      return TreePathspec::Nothing;
+    }
+    match Pathspec::new(rewritten) {
+      Ok(p) => TreePathspec::Tree(p),
+      Err(_) => TreePathspec::All,
+    }
+  }
+
+  // a commit can only change the matched paths if they exist in its tree or a parent's tree
+  fn may_match(&self, commit: &Commit) -> bool {
+    let pathspec = match self {
+      TreePathspec::All => return true,
+      TreePathspec::Nothing => return false,
+      TreePathspec::Tree(p) => p,
+    };
+    let in_tree = |tree: Result<Tree, git2::Error>| match tree {
+      Ok(t) => pathspec
+        .match_tree(&t, PathspecFlags::DEFAULT)
+        .map(|m| m.entries().len() > 0)
+        .unwrap_or(true),
+      Err(_) => true,
+    };
+    in_tree(commit.tree()) || commit.parents().any(|p| in_tree(p.tree()))
+  }
+}
+
+// check if the commit adds or removes the object, the id can be abbreviated
+fn test_find_object(commit: &CommitWrapper, id: &str) -> bool {
+  let tree = commit.c.tree().ok();
+  let changes_object = |old_tree: Option<&Tree>| {
+    let diff = match commit.r.diff_tree_to_tree(old_tree, tree.as_ref(), None) {
+      Ok(d) => d,
+      Err(_) => return false,
+    };
+    let is_object = |oid: Oid| oid.to_string().starts_with(id);
+    let changed = diff.deltas().any(|d| {
+      let (old_id, new_id) = (d.old_file().id(), d.new_file().id());
+      old_id != new_id && (is_object(old_id) || is_object(new_id))
+    });
+    changed
+  };
+  if commit.c.parent_count() == 0 {
+    return changes_object(None);
+  }
+  commit
+    .c
+    .parents()
+    .any(|p| changes_object(p.tree().ok().as_ref()))
+}
+
+// a failed diff or a path outside the working path doesn't match
 fn test_pathspec(commit: &CommitWrapper, pathspec: &Pathspec, work_dir: &Path) -> bool {
-  return commit.c.parents().any(|p| {
-    commit
-      .r
-      .diff_tree_to_tree(
-        p.tree().ok().as_ref(),
-        commit.c.tree().ok().as_ref(),
-        Some(&mut DiffOptions::default()),
-      )
-      .unwrap()
-      .deltas()
-      .any(|d| {
-        let new_path = commit.p.join(d.new_file().path().unwrap());
-        if d.status() == Delta::Renamed {
-          let old_path = commit.p.join(d.old_file().path().unwrap());
-          pathspec.matches_path(
-            new_path.strip_prefix(work_dir).unwrap(),
-            PathspecFlags::DEFAULT,
-          ) || pathspec.matches_path(
-            old_path.strip_prefix(work_dir).unwrap(),
-            PathspecFlags::DEFAULT,
-          )
-        } else {
-          pathspec.matches_path(
-            new_path.strip_prefix(work_dir).unwrap(),
-            PathspecFlags::DEFAULT,
-          )
-        }
+  let matches = |path: Option<&Path>| {
+    path
+      .map(|p| commit.p.join(p))
+      .and_then(|p| {
+        p.strip_prefix(work_dir)
+          .ok()
+          .map(|p| pathspec.matches_path(p, PathspecFlags::DEFAULT))
       })
-  });
+      .unwrap_or(false)
+  };
+  commit.c.parents().any(|p| {
+    let diff = commit.r.diff_tree_to_tree(
+      p.tree().ok().as_ref(),
+      commit.c.tree().ok().as_ref(),
+      Some(&mut DiffOptions::default()),
+    );
+    match diff {
+      Ok(diff) => diff.deltas().any(|d| {
+        matches(d.new_file().path())
+          || (d.status() == Delta::Renamed && matches(d.old_file().path()))
+      }),
+      Err(_) => false,
+    }
+  })
 }
 
-pub fn show_log(repo: Repository, repo_dir: &Path, args: LogArgs) {
-  let org_repo_path = repo.workdir().unwrap().to_owned();
+pub fn show_log(repo: Repository, base_path: &Path, args: LogArgs) -> Result<(), GitSubError> {
+  let org_repo_path = repo.workdir().expect("Get workdir failed").to_owned();
   let mut repos: Vec<Repository>;
   let mut heads: Vec<CommitWrapper>;
   if let Some(rev) = &args.head {
     repos = Vec::new();
     heads = Vec::new();
-    let obj = repo
+    let rev_commit = repo
       .revparse_single(rev)
-      .unwrap_or_else(|_| err_exit!("Can't find the revision in the root repo."));
-    let rev = obj
-      .as_commit()
-      .unwrap_or_else(|| err_exit!("The revision is not a commit"));
+      .and_then(|o| o.peel_to_commit())
+      .map_err(|e| GitSubError::RevParse(rev.clone(), e))?;
     let mut oids = Vec::new();
-    collect_submodule_heads_with_rev(rev, &repo, &mut oids, &mut repos);
-    oids.push(rev.id());
-    drop(rev);
-    drop(obj);
+    collect_submodule_heads_with_rev(
+      &rev_commit,
+      &repo,
+      &mut oids,
+      &mut repos,
+      args.ignore_submodules,
+    )?;
+    oids.push(rev_commit.id());
+    drop(rev_commit);
     repos.push(repo);
     for (i, id) in oids.iter().enumerate() {
       heads.push(CommitWrapper::new_with_repo(
-        repos[i]
-          .find_commit(*id)
-          .expect("Can't find the commit in submodule"),
+        repos[i].find_commit(*id)?,
         &repos[i],
       ));
     }
   } else {
-    repos = collect_submodules(repo);
+    repos = collect_submodules(repo, args.ignore_submodules)?;
     heads = Vec::new();
-    collect_heads(&repos, &args, &mut heads);
+    collect_heads(&repos, &args, &mut heads)?;
+    if heads.is_empty() && !args.all {
+      println!("No commits yet");
+      return Ok(());
+    }
   }
 
-  let walker = CommitsWalker::new(heads);
-  let now: DateTime<Local> = Local::now();
+  let mailmaps: HashMap<&Path, Mailmap> = if args.use_mailmap {
+    repos
+      .iter()
+      .map(|r| Ok((r.workdir().expect("Get workdir failed"), r.mailmap()?)))
+      .collect::<Result<_, GitSubError>>()?
+  } else {
+    HashMap::new()
+  };
+  let decorations: HashMap<&Path, Decorations> = if args.decorate != DecorateMode::No {
+    repos
+      .iter()
+      .map(|r| {
+        (
+          r.workdir().expect("Get workdir failed"),
+          Decorations::new(r, args.decorate),
+        )
+      })
+      .collect()
+  } else {
+    HashMap::new()
+  };
+  // prune the commits by their trees before diffing them
+  let tree_pathspecs: HashMap<&Path, TreePathspec> = if args.pathspec.is_some() {
+    repos
+      .iter()
+      .map(|r| {
+        let path = r.workdir().expect("Get workdir failed");
+        let prefix = path.strip_prefix(&org_repo_path).unwrap_or(Path::new(""));
+        (path, TreePathspec::new(&args.pathspec_patterns, prefix))
+      })
+      .collect()
+  } else {
+    HashMap::new()
+  };
+  let walker = CommitsWalker::new(heads, args.deepen);
   let mut count = args.num;
+  if args.json || args.json_lines || args.porcelain {
+    disable_color();
+  }
+  if args.json && args.group_by.is_none() {
+    print!("[");
+  }
+  let mut first = true;
+  let mut groups: HashMap<String, usize> = HashMap::new();
+  let mut graph = if args.graph { Some(Graph::new()) } else { None };
+  let ctx = PrintContext {
+    base_path,
+    root_path: &org_repo_path,
+    now: Local::now(),
+    mailmaps,
+    decorations,
+    colors: if args.color_by_repo {
+      Some(RepoColors::new(&repos))
+    } else {
+      None
+    },
+    describers: if args.show_tags {
+      repos
+        .iter()
+        .map(|r| {
+          (
+            r.workdir().expect("Get workdir failed"),
+            TagDescriber::new(),
+          )
+        })
+        .collect()
+    } else {
+      HashMap::new()
+    },
+    args: &args,
+  };
 
   walker
+    .take_while(|commit| match args.since {
+      Some(since) if !args.since_as_filter => commit.t.seconds() >= since,
+      _ => true,
+    })
     .filter(|commit| {
-      if let Some(ref grep) = args.grep {
-        if !grep.is_match(commit.c.message().unwrap_or("")) {
+      if let Some(since) = args.since {
+        if commit.t.seconds() < since {
+          return false;
+        }
+      }
+      if args.grep.is_some() || args.author.is_some() {
+        let grep_match = match args.grep {
+          Some(ref grep) => grep.is_match(commit.c.message().unwrap_or("")),
+          None => true,
+        };
+        let author_match = match args.author {
+          Some(ref author) => {
+            let mailmap = ctx.mailmaps.get(commit.p);
+            author.is_match(&commit_idents(&commit.c, mailmap).0.to_string())
+          }
+          None => true,
+        };
+        if (grep_match && author_match) == args.invert_match {
           return false;
         }
       }
-      if let Some(ref author) = args.author {
-        if !author.is_match(&commit.c.author().to_string()) {
+      if !args.grep_trailers.is_empty() {
+        let trailers = parse_trailers(commit.c.message().unwrap_or(""));
+        if !args.grep_trailers.iter().all(|f| f.test(&trailers)) {
+          return false;
+        }
+      }
+      if let Some(ref id) = args.find_object {
+        if !test_find_object(commit, id) {
           return false;
         }
       }
       if let Some(ref pathspec) = args.pathspec {
-        if !test_pathspec(&commit, &pathspec, &org_repo_path) {
+        let may_match = match tree_pathspecs.get(commit.p) {
+          Some(p) => p.may_match(&commit.c),
+          None => true,
+        };
+        if !may_match || !test_pathspec(commit, pathspec, &org_repo_path) {
           return false;
         }
       }
@@ -525,6 +1470,67 @@ pub fn show_log(repo: Repository, repo_dir: &Path, args: LogArgs) {
       }
     })
     .for_each(|c| {
-      print_commit(c, repo_dir, now, &args);
+      let mailmap = ctx.mailmaps.get(c.p);
+      if let Some(ref group_by) = args.group_by {
+        let key = if group_by == "repo" {
+          commit_repo_str(&c, base_path)
+        } else {
+          commit_idents(&c.c, mailmap).0.to_string()
+        };
+        *groups.entry(key).or_default() += 1;
+      } else if args.json {
+        // stream the array instead of collecting all the commits first
+        print!(
+          "{}\n  {}",
+          if first { "" } else { "," },
+          commit_json(&c, base_path, mailmap, true)
+        );
+      } else if args.json_lines {
+        println!("{}", commit_json(&c, base_path, mailmap, false));
+      } else if args.porcelain {
+        print_commit_porcelain(&c, base_path);
+      } else {
+        if let Some(ref mut g) = graph {
+          g.print_row(&c.c, c.p, ctx.colors.as_ref());
+        }
+        print_commit(c, &ctx);
+        if let Some(ref mut g) = graph {
+          g.print_after(ctx.colors.as_ref());
+        }
+      }
+      first = false;
     });
+  if let Some(ref group_by) = args.group_by {
+    print_groups(groups, group_by, args.json);
+  } else if args.json {
+    println!("{}]", if first { "" } else { "\n" });
+  }
+  Ok(())
+}
+
+// print the number of commits per group like `git shortlog -sn`, the largest first
+fn print_groups(groups: HashMap<String, usize>, group_by: &str, json: bool) {
+  let mut groups: Vec<(String, usize)> = groups.into_iter().collect();
+  groups.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+  if json {
+    let items: Vec<String> = groups
+      .iter()
+      .map(|(key, n)| {
+        json::json_object(
+          &[(group_by, json::json_str(key)), ("commits", n.to_string())],
+          true,
+          "  ",
+        )
+      })
+      .collect();
+    if items.is_empty() {
+      println!("[]");
+    } else {
+      println!("[\n  {}\n]", items.join(",\n  "));
+    }
+    return;
+  }
+  for (key, n) in groups.iter() {
+    println!("{}  {}", format!("{:>6}", n).green(), key.bright_blue());
+  }
 }
@@ -1,10 +1,43 @@
 use super::*;
 use clap::*;
 use git2::{Pathspec, Repository};
+use std::cell::{Cell, RefCell};
+use std::thread;
 pub struct LsArgs {
   staged: bool,
   pathspec: Option<Pathspec>,
+  // kept to build the pathspec again in the workers, it can't be shared between threads
+  pathspec_patterns: Vec<String>,
   rev: Option<String>,
+  tree: bool,
+  count_only: bool,
+  sha_path: bool,
+  separators: Separators,
+  jobs: usize,
+  others: bool,
+  ignored: bool,
+  submodule_order: SubmoduleOrder,
+  stdin_paths: bool,
+  error_unmatch: bool,
+  json: bool,
+  with_size: bool,
+  with_mode: bool,
+  annotate_repo: bool,
+  // print the nearest tag of the recorded commit before the files of each submodule
+  describe: bool,
+  // the submodule levels to descend into, the deeper gitlinks are listed as entries
+  max_depth: Option<usize>,
+  // the canonical root of the repo when the paths are shown relative to --relative-base
+  display_root: Option<PathBuf>,
+}
+
+// where the files of the submodules go relative to the files of their parent repo
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum SubmoduleOrder {
+  // at the place of the gitlink in the tree
+  Inline,
+  First,
+  Last,
 }
 
 impl LsArgs {
@@ -29,6 +62,123 @@ impl LsArgs {
           .short('r')
           .help("Search commits starting from the specific reference of the **root** repo"),
       )
+      .arg(
+        Arg::new("tree")
+          .long("tree")
+          .short('t')
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all(["null", "field-separator"])
+          .help("Indent files by the submodule depth and show submodules as headers"),
+      )
+      .arg(
+        Arg::new("count-only")
+          .long("count-only")
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all(["tree", "null", "field-separator"])
+          .help("Only print the number of files"),
+      )
+      .arg(
+        Arg::new("format")
+          .long("format")
+          .value_parser(["sha-path"])
+          .conflicts_with_all(["tree", "count-only"])
+          .help("Print `<sha><TAB><path>` for regular files only, leaving out the gitlinks"),
+      )
+      .args(separator_args())
+      .arg(
+        Arg::new("jobs")
+          .long("jobs")
+          .short('j')
+          .value_name("n")
+          .conflicts_with("tree")
+          .help("List the submodules of the root repo with n threads"),
+      )
+      .arg(
+        Arg::new("others")
+          .long("others")
+          .short('o')
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all(["staged", "revision", "tree", "format", "jobs"])
+          .help("List the untracked files in the working trees instead"),
+      )
+      .arg(
+        Arg::new("ignored")
+          .long("ignored")
+          .short('i')
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all(["staged", "revision", "tree", "format", "jobs"])
+          .help("List the ignored files in the working trees instead, can be used with --others"),
+      )
+      .arg(
+        Arg::new("submodules-first")
+          .long("submodules-first")
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all(["submodules-last", "others", "ignored"])
+          .help("List the files of the submodules before the files of their parent repo"),
+      )
+      .arg(
+        Arg::new("submodules-last")
+          .long("submodules-last")
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all(["others", "ignored"])
+          .help("List the files of the submodules after the files of their parent repo"),
+      )
+      .arg(
+        Arg::new("stdin-paths")
+          .long("stdin-paths")
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all(["pathspec", "tree", "count-only", "jobs", "others", "ignored"])
+          .help("Read paths relative to the root repo from stdin and print the object id of each, or that it's not tracked"),
+      )
+      .arg(
+        Arg::new("error-unmatch")
+          .long("error-unmatch")
+          .action(ArgAction::SetTrue)
+          .requires("stdin-paths")
+          .help("Exit with 1 if any of the paths is not tracked"),
+      )
+      .arg(
+        Arg::new("json")
+          .long("json")
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all(["tree", "count-only", "format", "null", "field-separator", "jobs", "others", "ignored", "stdin-paths"])
+          .help("Print the files as a json array of {path, oid, type, submodule_path}"),
+      )
+      .arg(
+        Arg::new("size")
+          .long("size")
+          .action(ArgAction::SetTrue)
+          .requires("json")
+          .help("Add the size of the blobs to the json records"),
+      )
+      .arg(
+        Arg::new("file-mode")
+          .long("mode")
+          .action(ArgAction::SetTrue)
+          .requires("json")
+          .help("Add the file mode to the json records"),
+      )
+      .arg(
+        Arg::new("max-depth")
+          .long("max-depth")
+          .value_name("n")
+          .conflicts_with_all(["others", "ignored", "stdin-paths"])
+          .help("Descend into n levels of submodules, the deeper gitlinks are listed as `<oid> <path>`; 0 lists the root repo only"),
+      )
+      .arg(
+        Arg::new("annotate-repo")
+          .long("annotate-repo")
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all(["tree", "format", "json", "others", "ignored", "stdin-paths"])
+          .help("Prefix each line with the repo the object id belongs to, the path follows relative to that repo"),
+      )
+      .arg(
+        Arg::new("describe")
+          .long("describe")
+          .action(ArgAction::SetTrue)
+          .conflicts_with_all(["count-only", "format", "json", "others", "ignored", "stdin-paths"])
+          .help("Print `# <path> @ <tag> (<short id>)` before the files of each submodule, from the nearest tag of its recorded commit"),
+      )
       .group(ArgGroup::new("mode").arg("staged").arg("revision"));
   }
 }
@@ -37,44 +187,347 @@ impl From<&clap::ArgMatches> for LsArgs {
   fn from(matches: &clap::ArgMatches) -> LsArgs {
     return LsArgs {
       staged: matches.get_flag("staged"),
-      pathspec: matches
+      pathspec: matches.get_many::<String>("pathspec").map(|s| {
+        Pathspec::new(s.map(|p| anchor_pathspec(p)))
+          .unwrap_or_else(|_| err_exit!("Crate pathspec failed"))
+      }),
+      pathspec_patterns: matches
         .get_many::<String>("pathspec")
-        .map(|s| Pathspec::new(s).unwrap_or_else(|_| err_exit!("Crate pathspec failed"))),
+        .map(|s| s.map(|p| anchor_pathspec(p)).collect())
+        .unwrap_or_default(),
       rev: matches.get_one::<String>("revision").map(|s| s.into()),
+      tree: matches.get_flag("tree"),
+      count_only: matches.get_flag("count-only"),
+      sha_path: matches.get_one::<String>("format").is_some(),
+      separators: Separators::from(matches),
+      jobs: matches.get_one::<String>("jobs").map_or(1, |s| {
+        s.parse::<usize>()
+          .unwrap_or_else(|e| err_exit!("Error while parsing --jobs option: {}", e))
+      }),
+      others: matches.get_flag("others"),
+      ignored: matches.get_flag("ignored"),
+      submodule_order: if matches.get_flag("submodules-first") {
+        SubmoduleOrder::First
+      } else if matches.get_flag("submodules-last") {
+        SubmoduleOrder::Last
+      } else {
+        SubmoduleOrder::Inline
+      },
+      stdin_paths: matches.get_flag("stdin-paths"),
+      error_unmatch: matches.get_flag("error-unmatch"),
+      json: matches.get_flag("json"),
+      with_size: matches.get_flag("size"),
+      with_mode: matches.get_flag("file-mode"),
+      annotate_repo: matches.get_flag("annotate-repo"),
+      describe: matches.get_flag("describe"),
+      max_depth: matches.get_one::<String>("max-depth").map(|s| {
+        s.parse::<usize>()
+          .unwrap_or_else(|e| err_exit!("Error while parsing --max-depth option: {}", e))
+      }),
+      display_root: None,
+    };
+  }
+}
+
+impl LsArgs {
+  // build the copy of the arguments in a worker thread, which lists its submodule sequentially
+  fn for_worker(&self) -> impl FnOnce() -> LsArgs + Send {
+    let patterns = self.pathspec_patterns.clone();
+    let has_pathspec = self.pathspec.is_some();
+    let (staged, rev, tree) = (self.staged, self.rev.clone(), self.tree);
+    let (count_only, sha_path) = (self.count_only, self.sha_path);
+    let separators = self.separators.clone();
+    let (submodule_order, annotate_repo) = (self.submodule_order, self.annotate_repo);
+    let describe = self.describe;
+    let max_depth = self.max_depth;
+    let display_root = self.display_root.clone();
+    move || LsArgs {
+      staged,
+      pathspec: if has_pathspec {
+        Some(Pathspec::new(patterns.iter()).unwrap_or_else(|_| err_exit!("Crate pathspec failed")))
+      } else {
+        None
+      },
+      pathspec_patterns: patterns,
+      rev,
+      tree,
+      count_only,
+      sha_path,
+      separators,
+      jobs: 1,
+      others: false,
+      ignored: false,
+      submodule_order,
+      stdin_paths: false,
+      error_unmatch: false,
+      json: false,
+      with_size: false,
+      with_mode: false,
+      annotate_repo,
+      describe,
+      max_depth,
+      display_root,
+    }
+  }
+}
+
+// a part of the output listed with --jobs, printed in the order of the tree at the end
+enum Chunk {
+  Done(String, usize),
+  Running(thread::JoinHandle<(String, usize)>),
+}
+
+thread_local! {
+  // the listing is collected here instead of printed while listing in parallel
+  static OUTPUT: RefCell<Option<String>> = const { RefCell::new(None) };
+  static CHUNKS: RefCell<Vec<Chunk>> = const { RefCell::new(Vec::new()) };
+  // no record is printed yet in the json array, to put the commas between them
+  static JSON_FIRST: Cell<bool> = const { Cell::new(true) };
+}
+
+fn emit(s: &str) {
+  OUTPUT.with(|o| match o.borrow_mut().as_mut() {
+    Some(buf) => buf.push_str(s),
+    None => print!("{}", s),
+  });
+}
+
+fn take_output() -> String {
+  OUTPUT.with(|o| o.borrow_mut().replace(String::new()).unwrap_or_default())
+}
+
+// list the submodule in a worker thread with --jobs, the files are counted when it is joined
+fn list_submodule(
+  repo: Repository,
+  commit: Oid,
+  base_path: &str,
+  args: &LsArgs,
+  depth: usize,
+) -> usize {
+  if args.jobs <= 1 {
+    return list_commit_file(repo, commit, Some(base_path), args, depth);
+  }
+  CHUNKS.with(|c| {
+    let mut chunks = c.borrow_mut();
+    chunks.push(Chunk::Done(take_output(), 0));
+    // wait for the oldest worker to keep at most `jobs` of them running
+    let running = chunks
+      .iter()
+      .filter(|c| matches!(c, Chunk::Running(_)))
+      .count();
+    if running >= args.jobs {
+      if let Some(chunk) = chunks.iter_mut().find(|c| matches!(c, Chunk::Running(_))) {
+        if let Chunk::Running(handle) = std::mem::replace(chunk, Chunk::Done(String::new(), 0)) {
+          let (text, count) = handle.join().expect("Join worker failed");
+          *chunk = Chunk::Done(text, count);
+        }
+      }
+    }
+    let build_args = args.for_worker();
+    let base_path = String::from(base_path);
+    chunks.push(Chunk::Running(thread::spawn(move || {
+      OUTPUT.with(|o| *o.borrow_mut() = Some(String::new()));
+      let count = list_commit_file(repo, commit, Some(&base_path), &build_args(), depth);
+      (take_output(), count)
+    })));
+  });
+  0
+}
+
+// print the output of the workers in order, return the number of files they listed
+fn finish_chunks() -> usize {
+  let text = take_output();
+  OUTPUT.with(|o| *o.borrow_mut() = None);
+  let mut count = 0;
+  for chunk in CHUNKS.with(|c| c.take()) {
+    let (text, n) = match chunk {
+      Chunk::Done(text, n) => (text, n),
+      Chunk::Running(handle) => handle.join().expect("Join worker failed"),
     };
+    print!("{}", text);
+    count += n;
   }
+  print!("{}", text);
+  count
 }
 
-fn list_index_file(repo: Repository, args: &LsArgs) {
+// the listing functions return the number of the listed files
+fn list_index_file(repo: Repository, args: &LsArgs) -> usize {
   // file mode reference: https://github.com/git/git/blob/a08a83db2bf27f015bec9a435f6d73e223c21c5e/Documentation/technical/index-format.txt#L63
   const FILE_MODE_GIT_LINK: u32 = 0b1110;
   let index = repo.index().expect("Get index failed");
-  index.iter().for_each(|e| {
-    let path_str = String::from_utf8_lossy(&e.path);
-    if e.mode >> 12 == FILE_MODE_GIT_LINK {
-      let sub = repo
-        .find_submodule(&path_str)
-        .expect("Can't find submodule");
-      let sub_repo = sub.open().expect("Can't open submodule repo");
-      list_commit_file(sub_repo, &e.id.to_string(), None, args);
-    } else {
-      if args.staged {
-        print!("{} ", e.id.to_string());
+  let mut count = 0;
+  let is_link = |e: &IndexEntry| e.mode >> 12 == FILE_MODE_GIT_LINK;
+  // the gitlinks are listed in a pass of their own when they are moved first or last
+  // a pass lists only the gitlinks with Some(true), only the files with Some(false), or both
+  let passes = match args.submodule_order {
+    SubmoduleOrder::Inline => vec![None],
+    SubmoduleOrder::First => vec![Some(true), Some(false)],
+    SubmoduleOrder::Last => vec![Some(false), Some(true)],
+  };
+  for pass in passes {
+    for e in index
+      .iter()
+      .filter(|e| pass.is_none_or(|links| is_link(e) == links))
+    {
+      let path_str = String::from_utf8_lossy(&e.path);
+      if is_link(&e) {
+        // the files of the submodule are shown relative to the root repo as well
+        count += list_gitlink(&repo, e.id, &path_str, &path_str, args, 0);
+      } else {
+        count += print_entry(&repo, &e.id, e.mode, &path_str, None, args, 0);
       }
-      println!("{}", path_str);
     }
-  });
+  }
+  count
+}
+
+// depth is the submodule nesting level of the repo, used to indent the tree view
+// the revision is only resolved in the root repo, e.g. `HEAD@{2 days ago}` picks the root
+// commit of that time and its tree records the submodule commits of that time
+fn resolve_rev(repo: &Repository, rev: &str) -> Result<Oid, GitSubError> {
+  repo
+    .revparse_single(rev)
+    .and_then(|o| o.peel_to_commit())
+    .map(|c| c.id())
+    .map_err(|e| GitSubError::RevParse(String::from(rev), e))
 }
 
-fn list_commit_file(repo: Repository, commit: &str, base_path: Option<&str>, args: &LsArgs) {
-  let obj = repo
-    .revparse_single(commit)
-    .unwrap_or_else(|_| err_exit!("Find revision failed"));
-  let commit = obj
-    .peel_to_commit()
-    .unwrap_or_else(|_| err_exit!("The revision can't peel to a commit"));
+fn list_commit_file(
+  repo: Repository,
+  commit: Oid,
+  base_path: Option<&str>,
+  args: &LsArgs,
+  depth: usize,
+) -> usize {
+  let commit = repo.find_commit(commit).unwrap_or_else(|e| {
+    err_exit!(
+      "Find commit {} of {} failed: {}",
+      commit,
+      base_path.unwrap_or("."),
+      e.message()
+    )
+  });
   let tree = commit.tree().expect("Can't find the tree for the commit");
-  list_tree(&repo, &tree, base_path, args, None);
+  if args.count_only && args.pathspec.is_none() {
+    return count_tree(&repo, &tree, args, depth);
+  }
+  if args.submodule_order == SubmoduleOrder::Inline {
+    return list_tree(&repo, &tree, base_path, args, None, depth);
+  }
+  // list_tree leaves the gitlinks out, they are listed before or after the files
+  let mut gitlinks = Vec::new();
+  tree
+    .walk(TreeWalkMode::PreOrder, |root, e| {
+      if e.kind() == Some(ObjectType::Commit) {
+        gitlinks.push((e.id(), format!("{}{}", root, e.name().unwrap_or(""))));
+      }
+      TreeWalkResult::Ok
+    })
+    .expect("Walk tree failed");
+  let list_gitlinks = || -> usize {
+    gitlinks
+      .iter()
+      .map(|(id, path)| {
+        let sub_name = match base_path {
+          Some(p) => format!("{}/{}", p, path),
+          None => path.clone(),
+        };
+        list_gitlink(&repo, *id, &sub_name, path, args, depth)
+      })
+      .sum()
+  };
+  let mut count = 0;
+  if args.submodule_order == SubmoduleOrder::First {
+    count += list_gitlinks();
+  }
+  count += list_tree(&repo, &tree, base_path, args, None, depth);
+  if args.submodule_order == SubmoduleOrder::Last {
+    count += list_gitlinks();
+  }
+  count
+}
+
+// list the files of the submodule at the gitlink
+// `sub_name` is the path relative to the root repo and `path` is relative to the repo
+fn list_gitlink(
+  repo: &Repository,
+  id: Oid,
+  sub_name: &str,
+  path: &str,
+  args: &LsArgs,
+  depth: usize,
+) -> usize {
+  // the gitlink itself is listed when the submodule is beyond --max-depth or can't be opened
+  let list_link = || {
+    if args.sha_path {
+      return 0;
+    }
+    let parent = sub_name.strip_suffix(path).map(|p| p.trim_end_matches('/'));
+    let mode = u32::from(FileMode::Commit);
+    print_entry(
+      repo,
+      &id,
+      mode,
+      sub_name,
+      parent.filter(|p| !p.is_empty()),
+      args,
+      depth,
+    )
+  };
+  if args.max_depth.is_some_and(|max| depth >= max) {
+    return list_link();
+  }
+  // the gitlink may have no matching config in .gitmodules or not be checked out
+  match repo.find_submodule(path).and_then(|sub| sub.open()) {
+    Ok(sub_repo) => {
+      print_submodule_header(sub_name, depth, args);
+      if args.describe {
+        print_describe_header(&sub_repo, id, sub_name, depth, args);
+      }
+      list_submodule(sub_repo, id, sub_name, args, depth + 1)
+    }
+    Err(err) => {
+      warn_or_exit!(
+        "Warning: can't open submodule {}: {}",
+        sub_name,
+        err.message()
+      );
+      list_link()
+    }
+  }
+}
+
+// count the files without building their paths, only the submodules need them to be found
+fn count_tree(repo: &Repository, tree: &Tree, args: &LsArgs, depth: usize) -> usize {
+  let mut count = 0;
+  tree
+    .walk(TreeWalkMode::PreOrder, |root, e| {
+      match e.kind() {
+        Some(ObjectType::Blob) => count += 1,
+        Some(ObjectType::Commit) if args.max_depth.is_some_and(|max| depth >= max) => count += 1,
+        Some(ObjectType::Commit) => {
+          let path = format!("{}{}", root, e.name().unwrap_or_default());
+          match repo.find_submodule(&path).and_then(|sub| sub.open()) {
+            Ok(sub_repo) => {
+              let sub_tree = sub_repo
+                .find_commit(e.id())
+                .and_then(|c| c.tree())
+                .unwrap_or_else(|err| err_exit!("Find the tree of {} failed: {}", path, err));
+              count += count_tree(&sub_repo, &sub_tree, args, depth + 1);
+            }
+            Err(err) => {
+              warn_or_exit!("Warning: can't open submodule {}: {}", path, err.message());
+              count += 1;
+            }
+          }
+        }
+        _ => {}
+      }
+      TreeWalkResult::Ok
+    })
+    .expect("Walk tree failed");
+  count
 }
 
 fn list_tree(
@@ -83,7 +536,9 @@ fn list_tree(
   rel_path_by_root: Option<&str>,
   args: &LsArgs,
   rel_path_by_repo: Option<&str>,
-) {
+  depth: usize,
+) -> usize {
+  let mut count = 0;
   tree.iter().for_each(|e| {
     // the relative path by the root repo
     let sub_name = if let Some(p) = rel_path_by_root {
@@ -97,43 +552,361 @@ fn list_tree(
     } else {
       String::from(e.name().unwrap_or(""))
     };
+    // a tree should never record the git directory itself, skip it if it does
+    if e.name() == Some(".git") {
+      return;
+    }
     match e.kind().expect("Got an unknown entry") {
       ObjectType::Commit => {
-        let sub = repo
-          .find_submodule(&sub_repo_base)
-          .expect("Find submodule failed");
-        let sub_repo = sub.open().expect("Open submodule failed");
-        list_commit_file(sub_repo, &e.id().to_string(), Some(&sub_name), args);
+        if args.submodule_order == SubmoduleOrder::Inline {
+          count += list_gitlink(repo, e.id(), &sub_name, &sub_repo_base, args, depth);
+        }
       }
       ObjectType::Tree => {
         let obj = e.to_object(repo).expect("Find tree object failed");
         let sub_tree = obj.as_tree().expect("Convert object to tree failed");
 
-        list_tree(repo, sub_tree, Some(&sub_name), args, Some(&sub_repo_base));
+        count += list_tree(
+          repo,
+          sub_tree,
+          Some(&sub_name),
+          args,
+          Some(&sub_repo_base),
+          depth,
+        );
       }
       _ => {
-        if let Some(pathspec) = &args.pathspec {
-          let path = Path::new(&sub_name);
-          if !pathspec.matches_path(path, PathspecFlags::DEFAULT) {
-            return;
-          }
-        }
-        print!("{} ", e.id().to_string());
-        println!("{}", sub_name);
+        // the root relative path ends with the repo relative one, the rest is the submodule
+        let submodule = sub_name
+          .strip_suffix(&sub_repo_base)
+          .map(|p| p.trim_end_matches('/'))
+          .filter(|p| !p.is_empty());
+        count += print_entry(
+          repo,
+          &e.id(),
+          e.filemode() as u32,
+          &sub_name,
+          submodule,
+          args,
+          depth,
+        );
       }
     }
   });
+  count
+}
+
+// print a tree entry as "<oid> <path>" if it matches the pathspec, return 1 if it does
+// `submodule` is the path of the submodule the entry is in, None in the root repo
+fn print_entry(
+  repo: &Repository,
+  id: &Oid,
+  mode: u32,
+  path_str: &str,
+  submodule: Option<&str>,
+  args: &LsArgs,
+  depth: usize,
+) -> usize {
+  if let Some(pathspec) = &args.pathspec {
+    let path = Path::new(path_str);
+    if !pathspec.matches_path(path, PathspecFlags::DEFAULT) {
+      return 0;
+    }
+  }
+  if args.count_only {
+    return 1;
+  }
+  if args.json {
+    let first = JSON_FIRST.with(|f| f.replace(false));
+    emit(&format!(
+      "{}\n  {}",
+      if first { "" } else { "," },
+      entry_json(repo, id, mode, path_str, submodule, args)
+    ));
+    return 1;
+  }
+  if args.tree {
+    print!("{}", "  ".repeat(depth));
+  }
+  let end = args.separators.end();
+  let sep = args
+    .separators
+    .field(if args.sha_path { "\t" } else { " " });
+  if args.annotate_repo {
+    // the object is in the database of the submodule, so the path is given relative to it
+    let (repo_str, rel_path) = match submodule {
+      Some(sub) => (
+        display_name(sub, args),
+        path_str[sub.len()..].trim_start_matches('/'),
+      ),
+      None => (display_name(".", args), path_str),
+    };
+    emit(&format!("{}: {}{}{}{}", repo_str, id, sep, rel_path, end));
+    return 1;
+  }
+  emit(&format!(
+    "{}{}{}{}",
+    id,
+    sep,
+    display_name(path_str, args),
+    end
+  ));
+  1
+}
+
+// the entry as a one line json object, the size and the mode are added on request
+fn entry_json(
+  repo: &Repository,
+  id: &Oid,
+  mode: u32,
+  path_str: &str,
+  submodule: Option<&str>,
+  args: &LsArgs,
+) -> String {
+  let is_link = mode == u32::from(FileMode::Commit);
+  let mut fields = vec![
+    ("path", json::json_str(&display_name(path_str, args))),
+    ("oid", json::json_str(&id.to_string())),
+  ];
+  if args.with_mode {
+    fields.push(("mode", json::json_str(&format!("{:06o}", mode))));
+  }
+  if args.with_size {
+    // the gitlinks have no size, their commit is in another repo
+    let size = repo
+      .odb()
+      .and_then(|odb| odb.read_header(*id))
+      .ok()
+      .filter(|_| !is_link)
+      .map(|(size, _)| size.to_string());
+    fields.push(("size", size.unwrap_or_else(|| String::from("null"))));
+  }
+  fields.push((
+    "type",
+    json::json_str(if is_link { "commit" } else { "blob" }),
+  ));
+  fields.push((
+    "submodule_path",
+    submodule
+      .map(json::json_str)
+      .unwrap_or_else(|| String::from("null")),
+  ));
+  json::json_object(&fields, false, "")
+}
+
+// the path relative to the working path or --relative-base, a bare repo keeps the path in the repo
+fn display_name(path_str: &str, args: &LsArgs) -> String {
+  match args.display_root {
+    Some(ref root) => relative_path(&root.join(path_str), relative_base()),
+    None => String::from(path_str),
+  }
+}
+
+// the header of the submodule in the tree view
+fn print_submodule_header(path_str: &str, depth: usize, args: &LsArgs) {
+  if args.tree {
+    println!(
+      "{}{}",
+      "  ".repeat(depth),
+      format!("{}/", display_name(path_str, args)).bright_blue()
+    );
+  }
+}
+
+// the tag the recorded commit of the submodule is at or after, like "# vendor/foo @ v1.2 (1234567)"
+// nothing is printed if no tag reaches the commit
+fn print_describe_header(
+  sub_repo: &Repository,
+  id: Oid,
+  path_str: &str,
+  depth: usize,
+  args: &LsArgs,
+) {
+  let tag = sub_repo
+    .find_commit(id)
+    .ok()
+    .and_then(|c| TagDescriber::new().describe(&c));
+  if let Some(tag) = tag {
+    // in the tree view it goes under the header of the submodule, with its files
+    let indent = if args.tree {
+      "  ".repeat(depth + 1)
+    } else {
+      String::new()
+    };
+    let header = format!(
+      "# {} @ {} ({})",
+      display_name(path_str, args),
+      tag,
+      &id.to_string()[..7]
+    );
+    emit(&format!("{}{}\n", indent, header.yellow()));
+  }
+}
+
+// list the untracked or ignored files of the working tree of the repo and its submodules
+// `prefix` is the path of the repo relative to the root repo like "sub/a/"
+fn list_untracked(repo: &Repository, prefix: &str, args: &LsArgs) -> usize {
+  let mut opts = StatusOptions::new();
+  opts
+    .exclude_submodules(true)
+    .include_untracked(args.others)
+    .recurse_untracked_dirs(true)
+    .include_ignored(args.ignored)
+    .recurse_ignored_dirs(true);
+  let statuses = repo
+    .statuses(Some(&mut opts))
+    .unwrap_or_else(|e| err_exit!("Get status failed: {}", e));
+  let mut count = 0;
+  for st in statuses.iter() {
+    let listed =
+      (args.others && st.status().is_wt_new()) || (args.ignored && st.status().is_ignored());
+    if !listed {
+      continue;
+    }
+    let path_str = format!("{}{}", prefix, String::from_utf8_lossy(st.path_bytes()));
+    if let Some(pathspec) = &args.pathspec {
+      if !pathspec.matches_path(Path::new(&path_str), PathspecFlags::DEFAULT) {
+        continue;
+      }
+    }
+    count += 1;
+    if !args.count_only {
+      let end = args.separators.end();
+      print!("{}{}", display_name(&path_str, args), end);
+    }
+  }
+  for sub in repo
+    .submodules()
+    .unwrap_or_else(|e| err_exit!("Get submodules failed: {}", e))
+    .iter()
+  {
+    let sub_path = format!(
+      "{}{}",
+      prefix,
+      sub.path().display().to_string().replace("\\", "/")
+    );
+    match sub.open() {
+      Ok(sub_repo) => count += list_untracked(&sub_repo, &format!("{}/", sub_path), args),
+      Err(err) => warn_or_exit!(
+        "Warning: can't open submodule {}: {}",
+        sub_path,
+        err.message()
+      ),
+    }
+  }
+  count
+}
+
+// find the object of the path in the commit, or in the index if no commit is given
+// the path descends into the submodule at a gitlink along it, at the commit the gitlink records
+fn lookup_path(repo: &Repository, commit: Option<Oid>, path: &str) -> Option<Oid> {
+  // the object at the path and whether it's a gitlink
+  let find = |p: &str| -> Option<(Oid, bool)> {
+    match commit {
+      Some(id) => {
+        let tree = repo.find_commit(id).and_then(|c| c.tree()).ok()?;
+        let entry = tree.get_path(Path::new(p)).ok()?;
+        match entry.kind() {
+          Some(ObjectType::Tree) => None,
+          kind => Some((entry.id(), kind == Some(ObjectType::Commit))),
+        }
+      }
+      None => {
+        let entry = repo.index().ok()?.get_path(Path::new(p), 0)?;
+        Some((entry.id, entry.mode == u32::from(FileMode::Commit)))
+      }
+    }
+  };
+  if let Some((id, _)) = find(path) {
+    return Some(id);
+  }
+  for (i, _) in path.match_indices('/') {
+    let (sub_path, rest) = (&path[..i], &path[i + 1..]);
+    if let Some((id, true)) = find(sub_path) {
+      let sub_repo = match repo.find_submodule(sub_path).and_then(|sub| sub.open()) {
+        Ok(r) => r,
+        Err(err) => {
+          warn_or_exit!(
+            "Warning: can't open submodule {}: {}",
+            sub_path,
+            err.message()
+          );
+          return None;
+        }
+      };
+      return lookup_path(&sub_repo, commit.map(|_| id), rest);
+    }
+  }
+  None
 }
 
-pub fn list_files(repo: Repository, args: LsArgs) {
-  if args.staged {
-    list_index_file(repo, &args);
+// check the paths read from stdin like `git ls-files --error-unmatch`, without stopping at
+// the first missing one
+fn check_stdin_paths(repo: &Repository, commit: Option<Oid>, args: &LsArgs) -> bool {
+  let mut all_tracked = true;
+  for line in std::io::stdin().lines() {
+    let line = line.unwrap_or_else(|e| err_exit!("Read stdin failed: {}", e));
+    let path = line.trim_end_matches('\r').trim_end_matches('/');
+    if path.is_empty() {
+      continue;
+    }
+    match lookup_path(repo, commit, path) {
+      Some(id) => println!("{} {}", id, display_name(path, args)),
+      None => {
+        all_tracked = false;
+        println!("{}: not tracked", display_name(path, args));
+      }
+    }
+  }
+  all_tracked
+}
+
+pub fn list_files(repo: Repository, mut args: LsArgs) -> Result<(), GitSubError> {
+  args.display_root = repo.workdir().and_then(|p| p.canonicalize().ok());
+  if args.others || args.ignored {
+    let count = list_untracked(&repo, "", &args);
+    if args.count_only {
+      println!("{}", count);
+    }
+    return Ok(());
+  }
+  if args.stdin_paths {
+    let commit = match args.rev.as_deref() {
+      _ if args.staged => None,
+      Some(rev) => Some(resolve_rev(&repo, rev)?),
+      None => Some(resolve_rev(&repo, "HEAD")?),
+    };
+    if !check_stdin_paths(&repo, commit, &args) && args.error_unmatch {
+      std::process::exit(1);
+    }
+    return Ok(());
+  }
+  if args.jobs > 1 {
+    OUTPUT.with(|o| *o.borrow_mut() = Some(String::new()));
+  }
+  if args.json {
+    disable_color();
+    print!("[");
+  }
+  let mut count = if args.staged {
+    list_index_file(repo, &args)
   } else {
     let rev_str: &str = if let Some(s) = args.rev.as_ref() {
       s
     } else {
       "HEAD"
     };
-    list_commit_file(repo, &rev_str, None, &args);
+    let commit = resolve_rev(&repo, rev_str)?;
+    list_commit_file(repo, commit, None, &args, 0)
+  };
+  if args.jobs > 1 {
+    count += finish_chunks();
+  }
+  if args.count_only {
+    println!("{}", count);
+  }
+  if args.json {
+    println!("{}]", if count == 0 { "" } else { "\n" });
   }
+  Ok(())
 }
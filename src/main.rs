@@ -1,33 +1,149 @@
+mod blame;
 mod color;
+mod combined_diff;
+mod count_objects;
+mod date;
+mod decorate;
+mod diff;
 mod diff_filter;
+mod error;
+mod export;
+mod fetch;
+mod foreach;
+mod graph;
+mod json;
+mod label;
 mod log;
 mod ls_files;
+mod pager;
+mod separator;
+mod stat;
 mod status;
+mod trailer;
+mod tree;
+mod verify;
+mod width;
+mod word_diff;
+use blame::*;
 use clap::*;
 use color::*;
+use combined_diff::*;
+use count_objects::*;
+use date::*;
+use decorate::*;
+use diff::*;
 use diff_filter::DiffFilter;
+use error::*;
+use export::*;
+use fetch::*;
+use foreach::*;
 use git2::*;
+use graph::*;
+use label::*;
 use log::*;
 use ls_files::*;
+use pager::*;
+use separator::*;
+use stat::*;
 use status::*;
 use std::path::*;
+use trailer::*;
+use tree::*;
+use verify::*;
+use width::*;
+use word_diff::*;
 
 #[macro_export]
 macro_rules! err_exit {
   ( $( $x:expr ),* ) => {{
-    eprintln!($($x,)*);
+    $crate::color::print_error(&format!($($x,)*));
     std::process::exit(1);
   }};
 }
+
+// report the failure of a single submodule and skip it, abort instead in strict mode
+#[macro_export]
+macro_rules! warn_or_exit {
+  ( $( $x:expr ),* ) => {{
+    if $crate::is_strict() {
+      err_exit!($($x),*);
+    } else {
+      eprintln!($($x,)*);
+    }
+  }};
+}
+
+static mut STRICT: bool = false;
+pub fn is_strict() -> bool {
+  unsafe { STRICT }
+}
+
+// --minimal of the subcommand, the patches take extra time to find the smallest diff
+static mut MINIMAL_DIFF: bool = false;
+pub fn is_minimal_diff() -> bool {
+  unsafe { MINIMAL_DIFF }
+}
+
+// --crlf of the subcommand, how the CR of the CRLF line endings in the patches is printed
+static mut CRLF_MODE: CrlfMode = CrlfMode::Keep;
+pub fn crlf_mode() -> CrlfMode {
+  unsafe { CRLF_MODE }
+}
+
+// the directory given by --relative-base or the working path, the displayed paths are relative to it
+static RELATIVE_BASE: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+pub fn relative_base() -> &'static Path {
+  RELATIVE_BASE.get().expect("The relative base is not set")
+}
+
+// the working path relative to the root of the repo like "src/", pathspecs are anchored at it
+static PATHSPEC_PREFIX: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+// rewrite the pathspec given relative to the working path to be relative to the repo root like git
+// `:/` matches from the root of the repo regardless of the working path
+pub fn anchor_pathspec(pattern: &str) -> String {
+  let (prefix, pattern) = match pattern.strip_prefix(":/") {
+    Some(p) => ("", p),
+    None => (PATHSPEC_PREFIX.get().map_or("", String::as_str), pattern),
+  };
+  let mut parts: Vec<&str> = prefix.split('/').filter(|s| !s.is_empty()).collect();
+  for c in pattern.split('/') {
+    match c {
+      "" | "." => {}
+      ".." => {
+        parts.pop();
+      }
+      c => parts.push(c),
+    }
+  }
+  if parts.is_empty() {
+    // the whole repo
+    String::from("*")
+  } else if pattern.ends_with('/') {
+    format!("{}/", parts.join("/"))
+  } else {
+    parts.join("/")
+  }
+}
 enum Args {
   None,
   Status(StatusArgs),
   Log(LogArgs),
   LsFile(LsArgs),
+  CountObjects(CountArgs),
+  Fetch(FetchArgs),
+  Diff(DiffArgs),
+  Foreach(ForeachArgs),
+  Tree(TreeArgs),
+  Verify(VerifyArgs),
+  Export(ExportArgs),
+  Blame(BlameArgs),
 }
 
 // build application's cli argument
-fn build_arg() -> (Repository, PathBuf, Args) {
+// the repo is opened by the caller since some subcommands don't need it
+// the returned path is the base of the displayed paths
+fn build_arg() -> (Result<Repository, git2::Error>, PathBuf, Args) {
   let matches = Command::new("git-sub")
     .about("Collect information of submodules in a convenience way")
     .author("paddythepaddy@duck.com")
@@ -39,6 +155,16 @@ fn build_arg() -> (Repository, PathBuf, Args) {
         .help("The working path or the repository")
         .default_value("."),
     )
+    .arg(
+      Arg::new("git-dir")
+        .long("git-dir")
+        .help("The path to the git directory, default to $GIT_DIR"),
+    )
+    .arg(
+      Arg::new("work-tree")
+        .long("work-tree")
+        .help("The path to the working tree, default to $GIT_WORK_TREE"),
+    )
     .arg(
       Arg::new("force-color")
         .long("force-color")
@@ -46,27 +172,110 @@ fn build_arg() -> (Repository, PathBuf, Args) {
         .action(ArgAction::SetTrue)
         .help("Force print color even using pipeline"),
     )
+    .arg(
+      Arg::new("no-pager")
+        .long("no-pager")
+        .action(ArgAction::SetTrue)
+        .help("Don't pipe the output of log, diff and blame into the pager"),
+    )
+    .arg(
+      Arg::new("full-tree")
+        .long("full-tree")
+        .action(ArgAction::SetTrue)
+        .help("Match pathspecs from the root of the repo instead of the working path"),
+    )
+    .arg(
+      Arg::new("relative-base")
+        .long("relative-base")
+        .value_name("dir")
+        .help("Show the paths relative to the directory, default to the working path"),
+    )
+    .arg(
+      Arg::new("strict")
+        .long("strict")
+        .action(ArgAction::SetTrue)
+        .help("Abort on any submodule failure instead of skipping it"),
+    )
     .subcommand(StatusArgs::build_arg())
     .subcommand(LogArgs::build_arg())
     .subcommand(LsArgs::build_arg())
+    .subcommand(CountArgs::build_arg())
+    .subcommand(FetchArgs::build_arg())
+    .subcommand(DiffArgs::build_arg())
+    .subcommand(ForeachArgs::build_arg())
+    .subcommand(TreeArgs::build_arg())
+    .subcommand(VerifyArgs::build_arg())
+    .subcommand(ExportArgs::build_arg())
+    .subcommand(BlameArgs::build_arg())
     .get_matches();
-  let work_dir_path = Path::new(matches.get_one::<String>("path").unwrap_or_else(|| {
-    err_exit!("Extract argument failed");
+  // like git, the options take precedence over the environment variables
+  let git_dir = matches
+    .get_one::<String>("git-dir")
+    .cloned()
+    .or_else(|| std::env::var("GIT_DIR").ok());
+  let work_tree = matches
+    .get_one::<String>("work-tree")
+    .cloned()
+    .or_else(|| std::env::var("GIT_WORK_TREE").ok());
+  let work_dir_path = Path::new(work_tree.as_ref().unwrap_or_else(|| {
+    matches.get_one::<String>("path").unwrap_or_else(|| {
+      err_exit!("Extract argument failed");
+    })
   }))
   .canonicalize()
   .unwrap_or_else(|e| {
     err_exit!("Get canonicalize path failed: {}", e);
   });
 
-  let repo = Repository::open(&work_dir_path).unwrap_or_else(|e| {
-    err_exit!("Open repo failed, not a git repo? {}", e);
+  // like git, the repo is searched from the working path upward
+  let repo = Repository::open_ext(
+    git_dir
+      .as_deref()
+      .map_or(work_dir_path.as_path(), Path::new),
+    RepositoryOpenFlags::empty(),
+    &[] as &[&std::ffi::OsStr],
+  )
+  .and_then(|repo| {
+    if work_tree.is_some() {
+      repo.set_workdir(&work_dir_path, false)?;
+    }
+    Ok(repo)
   });
+  let prefix = match repo.as_ref().ok().and_then(|r| r.workdir()) {
+    Some(root) if !matches.get_flag("full-tree") => root
+      .canonicalize()
+      .ok()
+      .and_then(|root| {
+        work_dir_path
+          .strip_prefix(root)
+          .ok()
+          .map(|p| p.display().to_string().replace("\\", "/"))
+      })
+      .unwrap_or_default(),
+    _ => String::new(),
+  };
+  PATHSPEC_PREFIX.set(prefix).unwrap();
+  let base_path = match matches.get_one::<String>("relative-base") {
+    Some(dir) => Path::new(dir)
+      .canonicalize()
+      .unwrap_or_else(|e| err_exit!("Get canonicalize path of {} failed: {}", dir, e)),
+    None => work_dir_path,
+  };
+  RELATIVE_BASE.set(base_path.clone()).unwrap();
   let args: Args;
   if let Some((sub_name, sub_matches)) = matches.subcommand() {
     match sub_name {
       "status" => args = Args::Status(StatusArgs::from(sub_matches)),
       "log" => args = Args::Log(LogArgs::from(sub_matches)),
       "ls-files" => args = Args::LsFile(LsArgs::from(sub_matches)),
+      "count-objects" => args = Args::CountObjects(CountArgs::from(sub_matches)),
+      "fetch" => args = Args::Fetch(FetchArgs::from(sub_matches)),
+      "diff" => args = Args::Diff(DiffArgs::from(sub_matches)),
+      "foreach" => args = Args::Foreach(ForeachArgs::from(sub_matches)),
+      "tree" => args = Args::Tree(TreeArgs::from(sub_matches)),
+      "verify" => args = Args::Verify(VerifyArgs::from(sub_matches)),
+      "export" => args = Args::Export(ExportArgs::from(sub_matches)),
+      "blame" => args = Args::Blame(BlameArgs::from(sub_matches)),
       _ => {
         err_exit!("Unknown subcommand")
       }
@@ -77,39 +286,87 @@ fn build_arg() -> (Repository, PathBuf, Args) {
   if matches.get_flag("force-color") {
     std::env::set_var("CLICOLOR_FORCE", "1");
   }
-  check_tty();
+  let paged = match matches.subcommand_name() {
+    Some(sub_name) if PAGED_SUBCOMMANDS.contains(&sub_name) && !matches.get_flag("no-pager") => {
+      page_output(repo.as_ref().ok())
+    }
+    _ => false,
+  };
+  check_tty(paged);
+  unsafe {
+    STRICT = matches.get_flag("strict");
+    MINIMAL_DIFF = matches
+      .subcommand()
+      .and_then(|(_, m)| m.try_get_one::<bool>("minimal").ok().flatten().copied())
+      .unwrap_or(false);
+    CRLF_MODE = matches
+      .subcommand()
+      .and_then(|(_, m)| m.try_get_one::<String>("crlf").ok().flatten())
+      .map_or(CrlfMode::Keep, |s| CrlfMode::from(s.as_str()));
+  }
 
-  return (repo, work_dir_path, args);
+  (repo, base_path, args)
 }
 
 fn main() {
   // preparing
-  let (repo, work_dir_path, args) = build_arg();
+  let (repo, base_path, args) = build_arg();
+  if let Args::Diff(ref a) = args {
+    if a.is_no_index() {
+      diff_paths(a).unwrap_or_else(|e| exit_with(e));
+      return;
+    }
+  }
+  let repo = repo.unwrap_or_else(|e| exit_with(GitSubError::NotARepo(e)));
 
   // the work
   match args {
     Args::Status(mut a) => {
-      show_repo_status(
-        &repo,
-        &work_dir_path,
-        repo
-          .head()
-          .expect("Extract head failed")
-          .resolve()
-          .expect("Resolve reference failed")
-          .target()
-          .expect("Get oid failed"),
-        &mut a,
-      );
+      if let Some(rev) = a.revision.take() {
+        show_recorded_changes(&repo, &rev).unwrap_or_else(|e| exit_with(e));
+        return;
+      }
+      let dirty =
+        show_repo_status(&repo, &base_path, None, &mut a).unwrap_or_else(|e| exit_with(e));
+      a.print_totals();
+      if a.exit_code && dirty {
+        std::process::exit(1);
+      }
     }
     Args::Log(a) => {
-      show_log(repo, &work_dir_path, a);
+      show_log(repo, &base_path, a).unwrap_or_else(|e| exit_with(e));
     }
     Args::LsFile(a) => {
-      list_files(repo, a);
+      list_files(repo, a).unwrap_or_else(|e| exit_with(e));
+    }
+    Args::CountObjects(a) => {
+      count_objects(repo, &base_path, a).unwrap_or_else(|e| exit_with(e));
+    }
+    Args::Fetch(a) => {
+      fetch(repo, a).unwrap_or_else(|e| exit_with(e));
+    }
+    Args::Diff(a) => {
+      diff(repo, &base_path, a).unwrap_or_else(|e| exit_with(e));
+    }
+    Args::Foreach(a) => {
+      foreach(repo, a).unwrap_or_else(|e| exit_with(e));
+    }
+    Args::Tree(a) => {
+      show_tree(repo, &base_path, a).unwrap_or_else(|e| exit_with(e));
+    }
+    Args::Verify(a) => {
+      verify(repo, a).unwrap_or_else(|e| exit_with(e));
+    }
+    Args::Export(a) => {
+      export(repo, &base_path, a).unwrap_or_else(|e| exit_with(e));
+    }
+    Args::Blame(a) => {
+      blame(repo, a).unwrap_or_else(|e| exit_with(e));
     }
     Args::None => {
-      err_exit!("No subcommand is given. Supported subcommand: status, log, ls-files")
+      err_exit!(
+        "No subcommand is given. Supported subcommand: status, log, ls-files, count-objects, fetch, diff, foreach, tree, verify, export, blame"
+      )
     }
   }
 }
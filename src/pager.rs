@@ -0,0 +1,119 @@
+use super::*;
+use std::process;
+#[cfg(unix)]
+use std::sync::atomic::{AtomicI32, Ordering};
+
+// the subcommands whose output goes through the pager, like git
+pub const PAGED_SUBCOMMANDS: [&str; 3] = ["log", "diff", "blame"];
+
+// the pid of the pager stdout is written to, 0 if there is none or it has been waited for
+#[cfg(unix)]
+static PAGER_PID: AtomicI32 = AtomicI32::new(0);
+
+// the pager like git picks it: $GIT_PAGER, core.pager, $PAGER, then less
+// an empty pager or `cat` turns the paging off
+fn pager_command(repo: Option<&Repository>) -> Option<String> {
+  let config_pager = || {
+    repo
+      .map_or_else(Config::open_default, Repository::config)
+      .and_then(|c| c.get_string("core.pager"))
+      .ok()
+  };
+  let pager = std::env::var("GIT_PAGER")
+    .ok()
+    .or_else(config_pager)
+    .or_else(|| std::env::var("PAGER").ok())
+    .unwrap_or_else(|| String::from("less"));
+  let pager = pager.trim();
+  if pager.is_empty() || pager == "cat" {
+    None
+  } else {
+    Some(String::from(pager))
+  }
+}
+
+// start the pager and write stdout into it, true if the output goes to the pager
+// the output isn't paged if stdout isn't a terminal
+#[cfg(unix)]
+pub fn page_output(repo: Option<&Repository>) -> bool {
+  use std::os::unix::io::AsRawFd;
+  if !atty::is(atty::Stream::Stdout) {
+    return false;
+  }
+  let pager = match pager_command(repo) {
+    Some(p) => p,
+    None => return false,
+  };
+  let mut pager_command = shell_command(&pager);
+  // like git, less shows the colors, quits on a single screen and keeps the screen
+  if std::env::var_os("LESS").is_none() {
+    pager_command.env("LESS", "FRX");
+  }
+  if std::env::var_os("LV").is_none() {
+    pager_command.env("LV", "-c");
+  }
+  let mut pager_process = match pager_command.stdin(process::Stdio::piped()).spawn() {
+    Ok(p) => p,
+    Err(e) => {
+      eprintln!("Warning: can't start the pager {}: {}", pager, e);
+      return false;
+    }
+  };
+  let pager_stdin = pager_process.stdin.take().expect("Get pager stdin failed");
+  if unsafe { libc::dup2(pager_stdin.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+    eprintln!(
+      "Warning: can't write to the pager {}: {}",
+      pager,
+      std::io::Error::last_os_error()
+    );
+    drop(pager_stdin);
+    let _ = pager_process.wait();
+    return false;
+  }
+  // stdout is the only write end of the pipe left, the pager gets the end of input when it closes
+  drop(pager_stdin);
+  PAGER_PID.store(pager_process.id() as i32, Ordering::SeqCst);
+  unsafe {
+    libc::signal(
+      libc::SIGPIPE,
+      on_broken_pipe as extern "C" fn(libc::c_int) as libc::sighandler_t,
+    );
+    libc::atexit(close_pager);
+  }
+  true
+}
+
+// the standard handles can't be swapped without the unix calls, so the output isn't paged
+#[cfg(not(unix))]
+pub fn page_output(_repo: Option<&Repository>) -> bool {
+  false
+}
+
+#[cfg(unix)]
+fn wait_for_pager() {
+  let pid = PAGER_PID.swap(0, Ordering::SeqCst);
+  if pid <= 0 {
+    return;
+  }
+  let mut status = 0;
+  while unsafe { libc::waitpid(pid, &mut status, 0) } < 0
+    && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted
+  {}
+}
+
+// run at exit, by process::exit too: end the output and wait until the user quits the pager
+#[cfg(unix)]
+extern "C" fn close_pager() {
+  let _ = std::io::Write::flush(&mut std::io::stdout());
+  unsafe {
+    libc::close(libc::STDOUT_FILENO);
+  }
+  wait_for_pager();
+}
+
+// the user quit the pager before the whole output is written, which isn't a failure like in git
+#[cfg(unix)]
+extern "C" fn on_broken_pipe(_: libc::c_int) {
+  wait_for_pager();
+  unsafe { libc::_exit(0) }
+}
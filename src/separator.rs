@@ -0,0 +1,49 @@
+use clap::*;
+
+// the separators of the machine readable output, shared by ls-files, status --name-only and
+// log --name-status so the fields can be split the same way everywhere
+#[derive(Clone)]
+pub struct Separators {
+  field: Option<String>,
+  null: bool,
+}
+
+pub fn separator_args() -> Vec<Arg> {
+  vec![
+    Arg::new("field-separator")
+      .long("field-separator")
+      .value_name("sep")
+      .help("Separate the fields of each line with sep, `\\t` is taken as a tab"),
+    Arg::new("null")
+      .long("null")
+      .short('z')
+      .action(ArgAction::SetTrue)
+      .help("Terminate the lines with NUL instead of newline"),
+  ]
+}
+
+impl From<&clap::ArgMatches> for Separators {
+  fn from(matches: &clap::ArgMatches) -> Separators {
+    Separators {
+      field: matches
+        .get_one::<String>("field-separator")
+        .map(|s| s.replace("\\t", "\t")),
+      null: matches.get_flag("null"),
+    }
+  }
+}
+
+impl Separators {
+  // the field separator, or the default of the output format if it's not given
+  pub fn field<'a>(&'a self, default: &'a str) -> &'a str {
+    self.field.as_deref().unwrap_or(default)
+  }
+
+  pub fn end(&self) -> char {
+    if self.null {
+      '\0'
+    } else {
+      '\n'
+    }
+  }
+}
@@ -0,0 +1,232 @@
+use super::*;
+use terminal_size::{terminal_size, Width};
+
+// collect the changed lines of each file and print them like `git diff --stat`
+pub struct ChangeStat {
+  files: Vec<FileStat>,
+}
+
+struct FileStat {
+  path: String,
+  insertions: usize,
+  deletions: usize,
+  // the sizes before and after the change of a binary file
+  binary: Option<(usize, usize)>,
+}
+
+// the width of the stat output, fit the terminal if it can be detected
+pub fn default_stat_width() -> usize {
+  match terminal_size() {
+    Some((Width(w), _)) => w as usize,
+    None => 80,
+  }
+}
+
+pub fn parse_stat_width(matches: &clap::ArgMatches) -> usize {
+  match matches.get_one::<String>("stat-width") {
+    Some(s) => s
+      .parse::<usize>()
+      .unwrap_or_else(|e| err_exit!("Error while parsing --stat-width option: {}", e)),
+    None => default_stat_width(),
+  }
+}
+
+// keep the tail of the path since the file name is the most relevant part
+fn truncate_path(path: &str, width: usize) -> String {
+  let len = path.chars().count();
+  if len <= width {
+    return String::from(path);
+  }
+  if width <= 3 {
+    return ".".repeat(width);
+  }
+  let tail: String = path.chars().skip(len - (width - 3)).collect();
+  format!("...{}", tail)
+}
+
+// the permille of the changes a directory needs to be printed by --dirstat
+const DIRSTAT_PERMILLE: usize = 30;
+
+// print the directories under `base` with enough changes, children before their parents
+// return the changes left to be counted in the parent
+fn gather_dirstat(files: &[(&str, usize)], base: &str, total: usize) -> usize {
+  let mut damage = 0;
+  let mut i = 0;
+  while i < files.len() {
+    let rest = &files[i].0[base.len()..];
+    match rest.find('/') {
+      Some(slash) => {
+        // the files are sorted, so the ones of the subdirectory are next to each other
+        let dir = format!("{}{}", base, &rest[..=slash]);
+        let count = files[i..]
+          .iter()
+          .take_while(|(p, _)| p.starts_with(&dir))
+          .count();
+        damage += gather_dirstat(&files[i..i + count], &dir, total);
+        i += count;
+      }
+      None => {
+        damage += files[i].1;
+        i += 1;
+      }
+    }
+  }
+  if !base.is_empty() && damage * 1000 / total >= DIRSTAT_PERMILLE {
+    let permille = damage * 1000 / total;
+    println!("{:>4}.{}% {}", permille / 10, permille % 10, base);
+    return 0;
+  }
+  damage
+}
+
+impl ChangeStat {
+  pub fn new() -> ChangeStat {
+    ChangeStat { files: Vec::new() }
+  }
+
+  pub fn add(&mut self, path: String, insertions: usize, deletions: usize) {
+    self.files.push(FileStat {
+      path,
+      insertions,
+      deletions,
+      binary: None,
+    });
+  }
+
+  pub fn add_binary(&mut self, path: String, old_size: usize, new_size: usize) {
+    self.files.push(FileStat {
+      path,
+      insertions: 0,
+      deletions: 0,
+      binary: Some((old_size, new_size)),
+    });
+  }
+
+  // print the share of the changed lines under each directory like `git diff --dirstat`
+  // a directory with less than 3% of the changes is counted in its parent instead, and a
+  // directory which is printed isn't counted again in its parent
+  pub fn print_dirstat(&self) {
+    let mut files: Vec<(&str, usize)> = self
+      .files
+      .iter()
+      .map(|f| {
+        // a rename is counted in the directory it moves to
+        let path = f.path.rsplit(" => ").next().unwrap_or(&f.path);
+        let damage = match f.binary {
+          Some((old_size, new_size)) => old_size.abs_diff(new_size),
+          None => f.insertions + f.deletions,
+        };
+        (path, damage)
+      })
+      .collect();
+    files.sort();
+    let total: usize = files.iter().map(|(_, d)| d).sum();
+    if total > 0 {
+      gather_dirstat(&files, "", total);
+    }
+  }
+
+  pub fn print(&self, width: usize) {
+    if self.files.is_empty() {
+      return;
+    }
+    let max_change = self
+      .files
+      .iter()
+      .map(|f| f.insertions + f.deletions)
+      .max()
+      .unwrap_or(0);
+    let count_width = max_change.to_string().len();
+    let max_name = self
+      .files
+      .iter()
+      .map(|f| f.path.chars().count())
+      .max()
+      .unwrap_or(0);
+    // " <name> | <count> <graph>", leave at least 10 columns to the graph
+    let overhead = count_width + 5;
+    let name_width = max_name.min(width.saturating_sub(overhead + 10)).max(1);
+    let graph_width = width
+      .saturating_sub(overhead + name_width)
+      .max(1)
+      .min(max_change.max(1));
+
+    let (mut insertions, mut deletions) = (0, 0);
+    for file in self.files.iter() {
+      if let Some((old_size, new_size)) = file.binary {
+        println!(
+          " {:name_width$} | Bin {} -> {} bytes",
+          truncate_path(&file.path, name_width),
+          old_size,
+          new_size,
+          name_width = name_width
+        );
+        continue;
+      }
+      let (ins, del) = (&file.insertions, &file.deletions);
+      insertions += ins;
+      deletions += del;
+      let total = ins + del;
+      let (mut ins_bar, mut del_bar) = (*ins, *del);
+      if max_change > graph_width {
+        ins_bar = ins * graph_width / max_change;
+        del_bar = del * graph_width / max_change;
+        // a changed file always gets at least a single mark
+        if *ins > 0 && ins_bar == 0 {
+          ins_bar = 1;
+        }
+        if *del > 0 && del_bar == 0 {
+          del_bar = 1;
+        }
+      }
+      println!(
+        " {:name_width$} | {:>count_width$} {}{}",
+        truncate_path(&file.path, name_width),
+        total,
+        "+".repeat(ins_bar).green(),
+        "-".repeat(del_bar).red(),
+        name_width = name_width,
+        count_width = count_width
+      );
+    }
+    println!("{}", summary_line(self.files.len(), insertions, deletions));
+  }
+}
+
+// the last line of --stat worded like git, in the singular for one and without a zero part
+// unless both are zero
+fn summary_line(files: usize, insertions: usize, deletions: usize) -> String {
+  let plural =
+    |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+  let mut line = format!(" {} changed", plural(files, "file", "files"));
+  if files == 0 {
+    return line;
+  }
+  if insertions > 0 || deletions == 0 {
+    line += &format!(", {}(+)", plural(insertions, "insertion", "insertions"));
+  }
+  if deletions > 0 || insertions == 0 {
+    line += &format!(", {}(-)", plural(deletions, "deletion", "deletions"));
+  }
+  line
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn summary_line_matches_git() {
+    assert_eq!(summary_line(1, 1, 0), " 1 file changed, 1 insertion(+)");
+    assert_eq!(summary_line(1, 0, 1), " 1 file changed, 1 deletion(-)");
+    assert_eq!(
+      summary_line(2, 3, 1),
+      " 2 files changed, 3 insertions(+), 1 deletion(-)"
+    );
+    assert_eq!(
+      summary_line(1, 0, 0),
+      " 1 file changed, 0 insertions(+), 0 deletions(-)"
+    );
+    assert_eq!(summary_line(0, 0, 0), " 0 files changed");
+  }
+}
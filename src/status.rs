@@ -1,13 +1,57 @@
 use super::*;
 use clap::*;
+use std::collections::{HashMap, HashSet};
 
 pub struct StatusArgs {
   status_option: StatusOptions,
   diff_filter: DiffFilter,
   show_option: ShowOption,
   is_short: bool,
+  name_only: bool,
   show_patch: bool,
+  patch_summary: bool,
+  expand: bool,
+  show_stat: bool,
+  show_dirstat: bool,
+  submodule_diff: bool,
+  submodule_log: bool,
+  color_moved: bool,
+  word_diff: Option<WordDiffMode>,
+  root_paths: bool,
+  stat_width: usize,
   all: bool,
+  line: bool,
+  check_urls: bool,
+  ahead_of_recorded: bool,
+  since_orig: bool,
+  // the age beyond which the recorded commit of a submodule is flagged, from --stale
+  stale: Option<chrono::Duration>,
+  max_entries: Option<usize>,
+  ignore_submodules: bool,
+  date: DateMode,
+  quiet: bool,
+  pub exit_code: bool,
+  pub revision: Option<String>,
+  labels: LabelTheme,
+  // the options of the quick diff which can find the root repo clean before its full status,
+  // from --root-only, taken by the status of the root so the submodules get the full one
+  root_check: Option<DiffOptions>,
+  // the canonical paths of the directories whose changes are left out
+  exclude_dirs: Vec<PathBuf>,
+  separators: Separators,
+  // the width of the old paths of renames before the arrow, 0 takes the widest of the repo
+  name_width: Option<usize>,
+  // the counts added up over the repos for --totals
+  totals: Option<StatusTotals>,
+}
+
+// the changes of all repos, added up from the same counts the header of each repo shows
+#[derive(Default)]
+struct StatusTotals {
+  repos: usize,
+  dirty_repos: usize,
+  staged: usize,
+  work_tree: usize,
 }
 
 impl StatusArgs {
@@ -36,6 +80,13 @@ impl StatusArgs {
         .action(ArgAction::SetTrue)
         .help("Include ignored files"),
     )
+    .arg(
+      Arg::new("no-refresh")
+        .long("no-refresh")
+        .visible_alias("no-optional-locks")
+        .action(ArgAction::SetTrue)
+        .help("Never write the index or anything else in .git, for read-only or shared file systems\nAlso set by GIT_OPTIONAL_LOCKS=0"),
+    )
     .arg(
       Arg::new("diff-filter")
         .long("diff-filter")
@@ -49,6 +100,21 @@ impl StatusArgs {
         .action(ArgAction::SetTrue)
         .help("Only show summary of dirty submodules"),
     )
+    .arg(
+      Arg::new("name-only")
+        .long("name-only")
+        .action(ArgAction::SetTrue)
+        .conflicts_with_all(["short", "line", "patch", "stat", "patch-with-stat", "dirstat"])
+        .help("Only print the paths of the changed files relative to the working path, no diff is built"),
+    )
+    .arg(
+      Arg::new("line")
+        .long("line")
+        .short('l')
+        .action(ArgAction::SetTrue)
+        .conflicts_with_all(["short", "patch", "stat", "patch-with-stat", "dirstat"])
+        .help("Show a single line summary of each dirty submodule"),
+    )
     .arg(
       Arg::new("patch")
         .long("patch")
@@ -56,6 +122,69 @@ impl StatusArgs {
         .action(ArgAction::SetTrue)
         .help("Show patch"),
     )
+    .arg(
+      Arg::new("summary")
+        .long("summary")
+        .action(ArgAction::SetTrue)
+        .requires("patch")
+        .help("With --patch, only show the hunk count and the line changes of each file"),
+    )
+    .arg(
+      Arg::new("expand")
+        .long("expand")
+        .action(ArgAction::SetTrue)
+        .requires("summary")
+        .help("Show the hunks after the summary of each file"),
+    )
+    .arg(
+      Arg::new("stat")
+        .long("stat")
+        .action(ArgAction::SetTrue)
+        .help("Show diffstat"),
+    )
+    .arg(
+      Arg::new("patch-with-stat")
+        .long("patch-with-stat")
+        .action(ArgAction::SetTrue)
+        .help("Show diffstat followed by patch"),
+    )
+    .arg(
+      Arg::new("dirstat")
+        .long("dirstat")
+        .action(ArgAction::SetTrue)
+        .help("Show the share of the changed lines under each directory"),
+    )
+    .arg(
+      Arg::new("no-submodule-diff")
+        .long("no-submodule-diff")
+        .action(ArgAction::SetTrue)
+        .help("Don't show the patch of submodule pointer changes"),
+    )
+    .arg(
+      Arg::new("submodule")
+        .long("submodule")
+        .value_name("format")
+        .value_parser(["short", "log"])
+        .help("Show the commits between the old and new submodule pointers with log"),
+    )
+    .arg(
+      Arg::new("color-moved")
+        .long("color-moved")
+        .action(ArgAction::SetTrue)
+        .help("Show lines moved within a file in a different color"),
+    )
+    .arg(word_diff_arg())
+    .arg(
+      Arg::new("relative-paths-in-patch")
+        .long("relative-paths-in-patch")
+        .action(ArgAction::SetTrue)
+        .help("Prefix the paths in patch headers with the submodule path"),
+    )
+    .arg(
+      Arg::new("stat-width")
+        .long("stat-width")
+        .help("Set the width of the diffstat, default to the terminal width"),
+    )
     .arg(
       Arg::new("all")
         .long("all")
@@ -63,12 +192,114 @@ impl StatusArgs {
         .action(ArgAction::SetTrue)
         .help("Show all submodules regardless it is dirty or not"),
     )
+    .arg(
+      Arg::new("check-urls")
+        .long("check-urls")
+        .action(ArgAction::SetTrue)
+        .help("Warn if the origin of a submodule differs from the url in .gitmodules"),
+    )
+    .arg(
+      Arg::new("ahead-of-recorded")
+        .long("ahead-of-recorded")
+        .action(ArgAction::SetTrue)
+        .help("Show how many commits the head of a submodule is beyond the recorded commit"),
+    )
+    .arg(
+      Arg::new("since-orig")
+        .long("since-orig")
+        .action(ArgAction::SetTrue)
+        .help("Show the commits between ORIG_HEAD and HEAD of each repo, e.g. after a merge or rebase"),
+    )
+    .arg(
+      Arg::new("stale")
+        .long("stale")
+        .value_name("duration")
+        .help("Flag the submodules whose recorded commit is older than the duration, like \"6 months\""),
+    )
+    .arg(
+      Arg::new("ignore-submodules")
+        .long("ignore-submodules")
+        .value_parser(["none", "all"])
+        .help("With all, skip the submodules configured with `ignore = all`"),
+    )
+    .arg(
+      Arg::new("max-entries")
+        .long("max-entries")
+        .help("Limit the number of changes listed for each submodule"),
+    )
+    .arg(
+      Arg::new("date")
+        .long("date")
+        .value_parser(DateMode::VALUES)
+        .help("Show dates in relative or absolute format"),
+    )
+    .arg(
+      Arg::new("revision")
+        .long("revision")
+        .short('r')
+        .help("Show how the submodule pointers changed from the revision to HEAD instead"),
+    )
+    .arg(
+      Arg::new("exit-code")
+        .long("exit-code")
+        .action(ArgAction::SetTrue)
+        .help("Exit with 1 if any submodule is dirty"),
+    )
+    .arg(
+      Arg::new("quiet")
+        .long("quiet")
+        .short('q')
+        .action(ArgAction::SetTrue)
+        .help("Print nothing and stop at the first dirty submodule, implies --exit-code"),
+    )
+    .arg(
+      Arg::new("totals")
+        .long("totals")
+        .action(ArgAction::SetTrue)
+        .conflicts_with_all(["quiet", "name-only"])
+        .help("Print the changes added up over all repos at the end"),
+    )
+    .arg(
+      Arg::new("root-only")
+        .long("root-only")
+        .action(ArgAction::SetTrue)
+        .help("Check the root repo by a quick diff first and skip its full status when it's clean"),
+    )
+    .arg(
+      Arg::new("exclude-dir")
+        .long("exclude-dir")
+        .value_name("path")
+        .action(ArgAction::Append)
+        .help("Leave out the changes under the directory, relative to the current directory, can be given multiple times"),
+    )
+    .arg(
+      Arg::new("name-width")
+        .long("name-width")
+        .value_name("n")
+        .num_args(0..=1)
+        .default_missing_value("auto")
+        .help("Pad the old paths of renames to n so the arrows line up, default to the widest of each repo"),
+    )
+    .arg(minimal_arg())
+    .arg(crlf_arg())
+    .args(label_args())
+    .args(separator_args())
     .arg(
       Arg::new("pathspec")
-      .action(ArgAction::Set)
-      .help("Filter commits by the pathspec")
+      .action(ArgAction::Append)
+      .help("Filter files by the pathspec")
     );
   }
+
+  // print the changes added up by show_repo_status, if --totals is given
+  pub fn print_totals(&self) {
+    if let Some(t) = self.totals.as_ref() {
+      println!(
+        "Total: {} changes staged, {} changes in working tree, {} of {} repos dirty",
+        t.staged, t.work_tree, t.dirty_repos, t.repos
+      );
+    }
+  }
 }
 
 impl From<&clap::ArgMatches> for StatusArgs {
@@ -87,11 +318,42 @@ impl From<&clap::ArgMatches> for StatusArgs {
     } else {
       ShowOption::Both
     };
-    if let Some(p) = matches.get_one::<String>("pathspec") {
-      status_option.pathspec(p);
+    // the quick check sees the same files as the status, without the renames
+    let mut root_check = matches.get_flag("root-only").then(DiffOptions::new);
+    if let Some(ref mut o) = root_check {
+      o.include_untracked(true)
+        .ignore_submodules(true)
+        .skip_binary_check(true);
+    }
+    // each call adds one more pattern
+    for p in matches.get_many::<String>("pathspec").into_iter().flatten() {
+      status_option.pathspec(anchor_pathspec(p));
+      if let Some(ref mut o) = root_check {
+        o.pathspec(anchor_pathspec(p));
+      }
     }
     status_option.include_ignored(matches.get_flag("include-ignored"));
-    status_option.recurse_untracked_dirs(matches.get_flag("patch"));
+    // the stat cache of the index is never refreshed, so nothing under .git gets written
+    let no_refresh =
+      matches.get_flag("no-refresh") || std::env::var("GIT_OPTIONAL_LOCKS").is_ok_and(|v| v == "0");
+    if no_refresh {
+      status_option.update_index(false);
+    }
+    if let Some(ref mut o) = root_check {
+      o.include_ignored(matches.get_flag("include-ignored"))
+        .update_index(!no_refresh);
+    }
+    let patch_with_stat = matches.get_flag("patch-with-stat");
+    // the rename detection reads the blobs, the paths alone come from the index and the file stats
+    if matches.get_flag("name-only") {
+      status_option.renames_head_to_index(false);
+    }
+    status_option.recurse_untracked_dirs(
+      matches.get_flag("patch")
+        || matches.get_flag("stat")
+        || matches.get_flag("dirstat")
+        || patch_with_stat,
+    );
 
     // prepare diff filter
     let diff_filter = match matches.get_one::<String>("diff-filter") {
@@ -104,8 +366,65 @@ impl From<&clap::ArgMatches> for StatusArgs {
       diff_filter: diff_filter,
       show_option: show,
       is_short: matches.get_flag("short"),
-      show_patch: matches.get_flag("patch"),
+      name_only: matches.get_flag("name-only"),
+      show_patch: matches.get_flag("patch") || patch_with_stat,
+      patch_summary: matches.get_flag("summary"),
+      expand: matches.get_flag("expand"),
+      show_stat: matches.get_flag("stat") || patch_with_stat,
+      show_dirstat: matches.get_flag("dirstat"),
+      submodule_diff: !matches.get_flag("no-submodule-diff"),
+      submodule_log: matches.get_one::<String>("submodule").map(String::as_str) == Some("log"),
+      color_moved: matches.get_flag("color-moved"),
+      word_diff: parse_word_diff(matches),
+      root_paths: matches.get_flag("relative-paths-in-patch"),
+      stat_width: parse_stat_width(matches),
       all: matches.get_flag("all"),
+      line: matches.get_flag("line"),
+      check_urls: matches.get_flag("check-urls"),
+      ahead_of_recorded: matches.get_flag("ahead-of-recorded"),
+      since_orig: matches.get_flag("since-orig"),
+      stale: matches
+        .get_one::<String>("stale")
+        .map(|s| parse_duration(s).unwrap_or_else(|| err_exit!("Can't parse the duration: {}", s))),
+      ignore_submodules: matches
+        .get_one::<String>("ignore-submodules")
+        .map(String::as_str)
+        == Some("all"),
+      max_entries: matches.get_one::<String>("max-entries").map(|s| {
+        s.parse::<usize>()
+          .unwrap_or_else(|e| err_exit!("Error while parsing --max-entries option: {}", e))
+      }),
+      date: matches
+        .get_one::<String>("date")
+        .map(|s| DateMode::from(s))
+        .unwrap_or(DateMode::Relative),
+      quiet: matches.get_flag("quiet"),
+      revision: matches.get_one::<String>("revision").cloned(),
+      exit_code: matches.get_flag("exit-code") || matches.get_flag("quiet"),
+      labels: LabelTheme::from(matches),
+      root_check,
+      separators: Separators::from(matches),
+      totals: matches.get_flag("totals").then(StatusTotals::default),
+      name_width: matches
+        .get_one::<String>("name-width")
+        .map(|s| match s.as_str() {
+          "auto" => 0,
+          s => s
+            .parse::<usize>()
+            .unwrap_or_else(|e| err_exit!("Error while parsing --name-width option: {}", e)),
+        }),
+      exclude_dirs: matches
+        .get_many::<String>("exclude-dir")
+        .into_iter()
+        .flatten()
+        .map(|p| {
+          // the directory may be gone already, then it's taken as is
+          let path = std::env::current_dir()
+            .expect("Get current directory failed")
+            .join(p);
+          path.canonicalize().unwrap_or(path)
+        })
+        .collect(),
     };
   }
 }
@@ -132,32 +451,149 @@ fn is_staged(status: Status) -> bool {
 
 // print Statuses
 // callback to print diff patch
-fn print_callback(_: DiffDelta<'_>, _: Option<DiffHunk<'_>>, line: DiffLine<'_>) -> bool {
-  if line.origin() == 'F' || line.origin() == 'B' {
-    print!("{}", String::from_utf8_lossy(line.content()));
-  } else if line.origin() == 'H' {
-    print!("{}", String::from_utf8_lossy(line.content()).cyan());
+pub fn print_callback(_: DiffDelta<'_>, _: Option<DiffHunk<'_>>, line: DiffLine<'_>) -> bool {
+  print_diff_line(line.origin(), line.content(), false);
+  return true;
+}
+
+// print the hunk header like git, only the `@@ -a,b +c,d @@` part is colored and the
+// function context after it is in the default color
+fn print_hunk_header(header: &str) {
+  let (line, ending) = split_line_ending(header);
+  let end = line
+    .get(2..)
+    .and_then(|rest| rest.find("@@"))
+    .map_or(line.len(), |i| i + 4);
+  let (range, context) = line.split_at(end);
+  print!(
+    "{}{}{}",
+    range.paint(color_for("diff.hunk")),
+    context,
+    ending
+  );
+}
+
+// print a line of the patch, `moved` lines are shown in purple instead of red and green
+pub fn print_diff_line(origin: char, content: &[u8], moved: bool) {
+  if origin == 'F' || origin == 'B' {
+    print!("{}", String::from_utf8_lossy(content));
+  } else if origin == 'H' {
+    print_hunk_header(&String::from_utf8_lossy(content));
+  } else if origin != '+' && origin != '-' && origin != ' ' {
+    // the "\ No newline at end of file" marker is in the content already
+    print!("{}", String::from_utf8_lossy(content));
   } else {
-    let msg = format!(
-      "{} {}",
-      line.origin(),
-      String::from_utf8_lossy(line.content())
-    );
-    let colored_msg = if msg.starts_with('+') {
-      msg.green()
+    let content = String::from_utf8_lossy(content);
+    let (body, ending) = split_line_ending(&content);
+    let msg = format!("{}{}", origin, body);
+    let colored_msg = if moved && (origin == '+' || origin == '-') {
+      msg.purple()
+    } else if msg.starts_with('+') {
+      msg.paint(color_for("diff.add"))
     } else if msg.starts_with('-') {
-      msg.red()
+      msg.paint(color_for("diff.remove"))
     } else {
       msg.default()
     };
-    print!("{}", colored_msg);
+    print!("{}{}", colored_msg, ending);
+  }
+}
+
+// the patches of added and deleted files are built from an empty buffer,
+// rewrite their headers the way git does so the patch can be fed to `git apply`
+pub fn file_header(content: &[u8], added: bool, deleted: bool) -> String {
+  let header = String::from_utf8_lossy(content);
+  if !added && !deleted {
+    return header.into_owned();
+  }
+  let mut out = String::new();
+  for line in header.lines() {
+    if let Some(index) = line.strip_prefix("index ") {
+      let mut parts = index.split_whitespace();
+      let ids = parts.next().unwrap_or_default();
+      let mode = parts.next().unwrap_or("100644");
+      let (old_id, new_id) = ids.split_once("..").unwrap_or((ids, ids));
+      let zero = "0".repeat(old_id.len());
+      if added {
+        out += &format!("new file mode {}\nindex {}..{}\n", mode, zero, new_id);
+      } else {
+        out += &format!("deleted file mode {}\nindex {}..{}\n", mode, old_id, zero);
+      }
+    } else if added && line.starts_with("--- ") {
+      out += "--- /dev/null\n";
+    } else if deleted && line.starts_with("+++ ") {
+      out += "+++ /dev/null\n";
+    } else {
+      out += &format!("{}\n", line);
+    }
+  }
+  out
+}
+
+// print the patch, the removed lines showing up again as added lines are marked as moved
+fn print_patch_color_moved(patch: &mut Patch, new_file: bool, deleted_file: bool) {
+  let mut lines: Vec<(char, Vec<u8>)> = Vec::new();
+  patch
+    .print(&mut |_, _, line| {
+      lines.push((line.origin(), line.content().to_vec()));
+      true
+    })
+    .unwrap();
+  // blank lines are moved around all the time, don't count them
+  let contents = |origin: char| -> HashSet<&[u8]> {
+    lines
+      .iter()
+      .filter(|(o, c)| *o == origin && !c.iter().all(|b| b.is_ascii_whitespace()))
+      .map(|(_, c)| c.as_slice())
+      .collect()
+  };
+  let (removed, added) = (contents('-'), contents('+'));
+  for (origin, content) in lines.iter() {
+    let moved = match origin {
+      '-' => added.contains(content.as_slice()),
+      '+' => removed.contains(content.as_slice()),
+      _ => false,
+    };
+    if *origin == 'F' {
+      print!("{}", file_header(content, new_file, deleted_file));
+      continue;
+    }
+    print_diff_line(*origin, content, moved);
   }
-  return true;
 }
 
 // print patch
-pub fn print_patch<'a>(repo: &Repository, delta: &DiffDelta, status: Status) {
-  if delta.new_file().mode() == FileMode::Commit || delta.old_file().mode() == FileMode::Commit {
+// `prefix` is prepended to the paths in the headers, e.g. the path of the submodule
+// `word_diff` is the --word-diff mode, it's applied by the kind of the file
+pub fn print_patch(
+  repo: &Repository,
+  delta: &DiffDelta,
+  status: Status,
+  color_moved: bool,
+  word_diff: Option<WordDiffMode>,
+  submodule_log: bool,
+  prefix: &str,
+) {
+  if is_git_link(delta) && submodule_log {
+    let path = delta.new_file().path().or_else(|| delta.old_file().path());
+    if let Some(sub_repo) = path.and_then(|p| {
+      repo
+        .find_submodule(&p.display().to_string().replace("\\", "/"))
+        .and_then(|s| s.open())
+        .ok()
+    }) {
+      println!(
+        "Submodule {}{} {}..{}:",
+        prefix,
+        path.unwrap_or(Path::new("")).display(),
+        &delta.old_file().id().to_string()[..7],
+        &delta.new_file().id().to_string()[..7]
+      );
+      print_submodule_log(&sub_repo, delta.old_file().id(), delta.new_file().id());
+      return;
+    }
+  }
+  if is_git_link(delta) {
     let old_name = delta
       .old_file()
       .path()
@@ -169,8 +605,10 @@ pub fn print_patch<'a>(repo: &Repository, delta: &DiffDelta, status: Status) {
       .map(|p| p.to_owned())
       .unwrap_or(PathBuf::new());
     println!(
-      "diff --git a/{} b/{}",
+      "diff --git a/{}{} b/{}{}",
+      prefix,
       old_name.display(),
+      prefix,
       new_name.display()
     );
     println!(
@@ -178,8 +616,8 @@ pub fn print_patch<'a>(repo: &Repository, delta: &DiffDelta, status: Status) {
       &delta.old_file().id().to_string()[..7],
       &delta.new_file().id().to_string()[..7]
     );
-    println!("--- a/{}", old_name.display());
-    println!("+++ b/{}", new_name.display());
+    println!("--- a/{}{}", prefix, old_name.display());
+    println!("+++ b/{}{}", prefix, new_name.display());
     println!("{}", "@@ -1 +1 @@".cyan());
     println!(
       "{}",
@@ -191,6 +629,221 @@ pub fn print_patch<'a>(repo: &Repository, delta: &DiffDelta, status: Status) {
     );
     return;
   }
+  let added = status.is_wt_new() || status.is_index_new();
+  let deleted = status.is_wt_deleted() || status.is_index_deleted();
+  with_patch(repo, delta, status, prefix, |patch| {
+    let path = delta.new_file().path().or_else(|| delta.old_file().path());
+    let word_diff = path.and_then(|p| word_diff_for(word_diff, p));
+    if let Some(mode) = word_diff {
+      print_patch_words(patch, mode, added, deleted);
+    } else if color_moved {
+      print_patch_color_moved(patch, added, deleted);
+    } else {
+      patch
+        .print(&mut |_, _, line| {
+          if line.origin() == 'F' {
+            print!("{}", file_header(line.content(), added, deleted));
+          } else {
+            print_diff_line(line.origin(), line.content(), false);
+          }
+          true
+        })
+        .unwrap();
+    }
+  });
+}
+
+// print the hunk count and the line changes of the file, e.g. "src/a.rs: 3 hunks, +12 -4"
+// followed by the line range of each hunk, to find the way in a large patch
+fn print_patch_summary(repo: &Repository, delta: &DiffDelta, status: Status, prefix: &str) {
+  let path = delta
+    .new_file()
+    .path()
+    .or_else(|| delta.old_file().path())
+    .map(|p| p.display().to_string().replace("\\", "/"))
+    .unwrap_or_default();
+  if is_git_link(delta) {
+    println!(
+      "{}{}: submodule {}..{}",
+      prefix,
+      path,
+      &delta.old_file().id().to_string()[..7],
+      &delta.new_file().id().to_string()[..7]
+    );
+    return;
+  }
+  with_patch(repo, delta, status, prefix, |patch| {
+    if patch.delta().flags().is_binary() {
+      println!("{}{}: binary", prefix, path);
+      return;
+    }
+    let (_, insertions, deletions) = patch.line_stats().expect("Get line stats failed");
+    let hunks = patch.num_hunks();
+    println!(
+      "{}{}: {} hunk{}, {} {}",
+      prefix,
+      path,
+      hunks,
+      if hunks == 1 { "" } else { "s" },
+      format!("+{}", insertions).green(),
+      format!("-{}", deletions).red()
+    );
+    for h in 0..hunks {
+      let (hunk, _) = patch.hunk(h).expect("Get hunk failed");
+      println!(
+        "  {}",
+        format!(
+          "-{},{} +{},{}",
+          hunk.old_start(),
+          hunk.old_lines(),
+          hunk.new_start(),
+          hunk.new_lines()
+        )
+        .cyan()
+      );
+    }
+  });
+}
+
+// list the commits between two pointers of the submodule like `git diff --submodule=log`
+// `>` marks the commits added by the change and `<` the removed ones
+pub fn print_submodule_log(repo: &Repository, old: Oid, new: Oid) {
+  let walk = |from: Oid, hide: Oid| -> Option<Vec<Oid>> {
+    let mut walk = repo.revwalk().ok()?;
+    walk.push(from).ok()?;
+    if !hide.is_zero() {
+      walk.hide(hide).ok()?;
+    }
+    walk.collect::<Result<Vec<Oid>, git2::Error>>().ok()
+  };
+  let added = if new.is_zero() {
+    Some(Vec::new())
+  } else {
+    walk(new, old)
+  };
+  let removed = if old.is_zero() {
+    Some(Vec::new())
+  } else {
+    walk(old, new)
+  };
+  let (added, removed) = match (added, removed) {
+    (Some(a), Some(r)) => (a, r),
+    _ => {
+      println!("  {}", "(commits not present)".yellow());
+      return;
+    }
+  };
+  let print = |mark: &str, id: &Oid| {
+    let summary = repo
+      .find_commit(*id)
+      .ok()
+      .and_then(|c| c.summary().map(String::from))
+      .unwrap_or_default();
+    let line = format!("  {} {} {}", mark, &id.to_string()[..7], summary);
+    if mark == ">" {
+      println!("{}", line.green());
+    } else {
+      println!("{}", line.red());
+    }
+  };
+  removed.iter().for_each(|id| print("<", id));
+  added.iter().for_each(|id| print(">", id));
+}
+
+// check if the delta is a submodule pointer change
+pub fn is_git_link(delta: &DiffDelta) -> bool {
+  delta.new_file().mode() == FileMode::Commit || delta.old_file().mode() == FileMode::Commit
+}
+
+// check if the content is binary the way git does: a NUL byte in the first 8000 bytes
+pub fn is_binary(content: &[u8]) -> bool {
+  const BINARY_CHECK_LEN: usize = 8000;
+  content.iter().take(BINARY_CHECK_LEN).any(|b| *b == 0)
+}
+
+// the --minimal option of the subcommands printing patches
+pub fn minimal_arg() -> Arg {
+  Arg::new("minimal")
+    .long("minimal")
+    .action(ArgAction::SetTrue)
+    .help("Spend extra time to make the patches as small as possible")
+}
+
+// how the CR of a CRLF line ending is printed in the patches
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CrlfMode {
+  // as it is, so the patch still applies
+  Keep,
+  // as the visible ␍
+  Show,
+  // left out
+  Hide,
+}
+
+impl From<&str> for CrlfMode {
+  fn from(s: &str) -> CrlfMode {
+    match s {
+      "show" => CrlfMode::Show,
+      "hide" => CrlfMode::Hide,
+      _ => CrlfMode::Keep,
+    }
+  }
+}
+
+// the --crlf option of the subcommands printing patches
+pub fn crlf_arg() -> Arg {
+  Arg::new("crlf")
+    .long("crlf")
+    .value_name("mode")
+    .value_parser(["show", "hide"])
+    .help("Show the CR of CRLF line endings in the patches as ␍, or hide it")
+}
+
+// the line of a patch without its line ending, and the ending to print by --crlf
+// the ending goes after the color is reset, so a CR doesn't turn into ^M in the pager
+pub fn split_line_ending(line: &str) -> (&str, &'static str) {
+  if let Some(body) = line.strip_suffix("\r\n") {
+    let ending = match crlf_mode() {
+      CrlfMode::Keep => "\r\n",
+      CrlfMode::Show => "\u{240d}\n",
+      CrlfMode::Hide => "\n",
+    };
+    (body, ending)
+  } else if let Some(body) = line.strip_suffix('\n') {
+    (body, "\n")
+  } else {
+    (line, "")
+  }
+}
+
+// diff options treating both sides as binary if either of them is
+pub fn patch_options(old: &[u8], new: &[u8], prefix: &str) -> DiffOptions {
+  let mut opts = DiffOptions::new();
+  opts
+    .force_binary(is_binary(old) || is_binary(new))
+    .minimal(is_minimal_diff())
+    .old_prefix(format!("a/{}", prefix))
+    .new_prefix(format!("b/{}", prefix));
+  opts
+}
+
+// the path of the repo relative to the base path as the prefix of patch paths, e.g. "sub/a/"
+// a patch path can't go up, so a repo outside of the base has none
+pub fn patch_prefix(repo_dir: &Path, base_path: &Path) -> String {
+  match relative_path(repo_dir, base_path) {
+    p if p == "." || p == ".." || p.starts_with("../") || Path::new(&p).is_absolute() => {
+      String::new()
+    }
+    p => format!("{}/", p),
+  }
+}
+
+// build the patch of the delta and pass it to the callback
+// the patch borrows the file buffers, so it can't be returned
+pub fn with_patch<F>(repo: &Repository, delta: &DiffDelta, status: Status, prefix: &str, mut f: F)
+where
+  F: FnMut(&mut Patch),
+{
   let work_path = repo.workdir().expect("Get repo directory failed");
   if status.is_wt_new() {
     // new file case
@@ -198,10 +851,14 @@ pub fn print_patch<'a>(repo: &Repository, delta: &DiffDelta, status: Status) {
     // new file = working tree file
     let new_path = work_path.join(delta.new_file().path().expect("Get new file path failed"));
     let new_buffer = std::fs::read(&new_path).expect("Read new file failed");
-    Patch::from_buffers(&[], None, &new_buffer, delta.new_file().path(), None)
-      .expect("Get patch failed")
-      .print(&mut print_callback)
-      .unwrap();
+    f(&mut Patch::from_buffers(
+      &[],
+      None,
+      &new_buffer,
+      delta.new_file().path(),
+      Some(&mut patch_options(&[], &new_buffer, prefix)),
+    )
+    .expect("Get patch failed"));
   } else if status.is_index_new() {
     // new file in stage
     // old file = empty
@@ -211,37 +868,45 @@ pub fn print_patch<'a>(repo: &Repository, delta: &DiffDelta, status: Status) {
       .find_blob(delta.new_file().id())
       .expect("Find blob failed");
     let new_path = delta.new_file().path();
-    Patch::from_blob_and_buffer(
+    f(&mut Patch::from_blob_and_buffer(
       &new_blob,
       new_path,
       &[],
       None,
-      Some(DiffOptions::new().reverse(true)),
+      Some(patch_options(new_blob.content(), &[], prefix).reverse(true)),
     )
-    .expect("Get patch failed")
-    .print(&mut print_callback)
-    .unwrap();
+    .expect("Get patch failed"));
   } else {
     let old_blob = repo
       .find_blob(delta.old_file().id())
       .expect("Find blob failed");
     let old_path = delta.old_file().path();
-    if !is_staged(status) {
+    if status.is_index_deleted() || status.is_wt_deleted() {
+      // deleted file
+      // old file = blob
+      // new file = empty
+      f(&mut Patch::from_blob_and_buffer(
+        &old_blob,
+        old_path,
+        &[],
+        None,
+        Some(&mut patch_options(old_blob.content(), &[], prefix)),
+      )
+      .expect("Get patch failed"));
+    } else if !is_staged(status) {
       // work tree change
       // old file = blob (should from index)
       // new file = working tree file
       let new_path = work_path.join(delta.new_file().path().expect("Get new file path failed"));
       let new_buffer = std::fs::read(&new_path).expect("Read new file failed");
-      Patch::from_blob_and_buffer(
+      f(&mut Patch::from_blob_and_buffer(
         &old_blob,
         old_path,
         &new_buffer,
         delta.new_file().path(),
-        None,
+        Some(&mut patch_options(old_blob.content(), &new_buffer, prefix)),
       )
-      .expect("Get patch failed")
-      .print(&mut print_callback)
-      .unwrap();
+      .expect("Get patch failed"));
     } else {
       // staged change
       // old file = blob (should from HEAD)
@@ -250,194 +915,815 @@ pub fn print_patch<'a>(repo: &Repository, delta: &DiffDelta, status: Status) {
         .find_blob(delta.new_file().id())
         .expect("Find blob failed");
       let new_path = delta.new_file().path();
-      Patch::from_blobs(&old_blob, old_path, &new_blob, new_path, None)
-        .expect("Get patch failed")
-        .print(&mut print_callback)
-        .unwrap();
+      f(&mut Patch::from_blobs(
+        &old_blob,
+        old_path,
+        &new_blob,
+        new_path,
+        Some(&mut patch_options(
+          old_blob.content(),
+          new_blob.content(),
+          prefix,
+        )),
+      )
+      .expect("Get patch failed"));
     }
   }
 }
 
-// get the label of the change status
-fn status_to_str(status: Status) -> ColoredString {
-  if status.is_index_new() {
-    "A ".green()
-  } else if status.is_index_modified() {
-    "M ".green()
-  } else if status.is_index_deleted() {
-    "D ".green()
-  } else if status.is_index_renamed() {
-    "R ".green()
-  } else if status.is_index_typechange() {
-    "T ".green()
-  } else if status.is_wt_new() {
-    "??".red()
-  } else if status.is_wt_modified() {
-    " M".red()
-  } else if status.is_wt_deleted() {
-    " D".red()
-  } else if status.is_wt_typechange() {
-    " T".red()
-  } else if status.is_wt_renamed() {
-    " R".red()
-  } else if status.is_ignored() {
-    "!!".red()
+// add the changed lines of the delta to the stat
+pub fn add_stat(stat: &mut ChangeStat, repo: &Repository, delta: &DiffDelta, status: Status) {
+  let old_path = delta.old_file().path();
+  let new_path = delta.new_file().path();
+  let path = match (old_path, new_path) {
+    (Some(o), Some(n)) if o != n => format!("{} => {}", o.display(), n.display()),
+    _ => new_path
+      .or(old_path)
+      .map(|p| p.display().to_string())
+      .unwrap_or_default(),
+  };
+  if is_git_link(delta) {
+    // the pointer change is shown as one line replaced
+    stat.add(path, 1, 1);
+    return;
+  }
+  with_patch(repo, delta, status, "", |patch| {
+    let patch_delta = patch.delta();
+    if patch_delta.flags().is_binary() {
+      stat.add_binary(
+        path.clone(),
+        patch_delta.old_file().size() as usize,
+        patch_delta.new_file().size() as usize,
+      );
+      return;
+    }
+    let (_, insertions, deletions) = patch.line_stats().expect("Get line stats failed");
+    stat.add(path.clone(), insertions, deletions);
+  });
+}
+
+// detect renames in the diff
+// skip it when the changes exceed diff.renameLimit, like git, to avoid the quadratic comparing
+pub fn find_renames(repo: &Repository, diff: &mut Diff) {
+  const DEFAULT_RENAME_LIMIT: usize = 1000;
+  let limit = repo
+    .config()
+    .and_then(|c| c.get_i32("diff.renameLimit"))
+    .ok()
+    .filter(|l| *l > 0)
+    .map(|l| l as usize)
+    .unwrap_or(DEFAULT_RENAME_LIMIT);
+  let added = diff.deltas().filter(|d| d.status() == Delta::Added).count();
+  let deleted = diff
+    .deltas()
+    .filter(|d| d.status() == Delta::Deleted)
+    .count();
+  if added == 0 || deleted == 0 {
+    return;
+  }
+  if added * deleted > limit * limit {
+    eprintln!(
+      "Warning: skip rename detection, {} added and {} deleted files exceed diff.renameLimit ({})",
+      added, deleted, limit
+    );
+    return;
+  }
+  diff
+    .find_similar(Some(
+      DiffFindOptions::new().renames(true).rename_limit(limit),
+    ))
+    .expect("Find renames failed");
+}
+
+// the delta of the change, staged changes compare HEAD to index, otherwise index to working tree
+fn status_delta<'a>(st: &'a StatusEntry) -> DiffDelta<'a> {
+  if is_staged(st.status()) {
+    st.head_to_index().expect("Get head to index delta failed")
   } else {
-    "??".red()
+    st.index_to_workdir()
+      .expect("Get index to working tree delta failed")
   }
 }
 
-fn show_statuses(statuses: &Vec<StatusEntry>, repo: &Repository, patch: bool) {
+// the delta of the renamed entry, staged or in the working tree
+fn rename_delta<'a>(st: &'a StatusEntry) -> Option<DiffDelta<'a>> {
+  if st.status().is_index_renamed() {
+    st.head_to_index()
+  } else if st.status().is_wt_renamed() {
+    st.index_to_workdir()
+  } else {
+    None
+  }
+}
+
+// the width of the old paths of the renames in the listings of a repo
+fn old_path_width(statuses: &[&StatusEntry]) -> usize {
+  statuses
+    .iter()
+    .filter_map(|st| rename_delta(st))
+    .filter_map(|d| {
+      d.old_file()
+        .path()
+        .map(|p| text_width(&p.display().to_string()))
+    })
+    .max()
+    .unwrap_or(0)
+}
+
+// `shown` counts the changes listed in the repo so far, for --max-entries
+// the old paths of the renames are padded to `old_width`, so the arrows line up
+fn show_statuses(
+  statuses: &Vec<StatusEntry>,
+  repo: &Repository,
+  args: &StatusArgs,
+  base_path: &Path,
+  prefix: &str,
+  shown: &mut usize,
+  old_width: usize,
+) {
+  let repo_dir = repo
+    .workdir()
+    .and_then(|p| p.canonicalize().ok())
+    .unwrap_or_default();
+  let display = |path: &Path| relative_path(&repo_dir.join(path), base_path);
+  if args.show_stat || args.show_dirstat {
+    let mut stat = ChangeStat::new();
+    for st in statuses.iter() {
+      add_stat(&mut stat, repo, &status_delta(st), st.status());
+    }
+    if args.show_dirstat {
+      stat.print_dirstat();
+    }
+    if args.show_stat {
+      stat.print(args.stat_width);
+    }
+  }
+  let mut hidden = 0;
   for st in statuses.iter() {
-    if st.status().is_index_renamed() || st.status().is_wt_renamed() {
-      let delta = if st.status().is_index_renamed() {
-        st.head_to_index().expect("Get head to index delta failed")
-      } else {
-        st.index_to_workdir()
-          .expect("Get index to working tree delta failed")
-      };
+    if matches!(args.max_entries, Some(max) if *shown >= max) {
+      hidden += 1;
+      continue;
+    }
+    *shown += 1;
+    if let Some(delta) = rename_delta(st) {
       let old_file = delta.old_file().path().expect("Get old file path failed");
       let new_file = delta.new_file().path().expect("Get new file path failed");
       println!(
         " {} {} -> {}",
-        status_to_str(st.status()),
-        old_file.display(),
-        new_file.display()
+        args.labels.status_label(st.status()),
+        pad_to_width(&display(old_file), old_width),
+        display(new_file)
       );
     } else {
+      let path = st.path().unwrap_or_else(|| {
+        err_exit!("Extract path failed");
+      });
       println!(
         " {} {}",
-        status_to_str(st.status()),
-        st.path().unwrap_or_else(|| {
-          err_exit!("Extract path failed");
-        })
+        args.labels.status_label(st.status()),
+        display(Path::new(path))
       );
     }
-    if patch {
-      let delta = if is_staged(st.status()) {
-        st.head_to_index().expect("Get head to index delta failed")
-      } else {
-        st.index_to_workdir()
-          .expect("Get index to working tree delta failed")
-      };
+    if args.show_patch {
+      let delta = status_delta(st);
+      if args.submodule_diff || !is_git_link(&delta) {
+        if args.patch_summary {
+          print_patch_summary(repo, &delta, st.status(), prefix);
+        }
+        if !args.patch_summary || args.expand {
+          print_patch(
+            repo,
+            &delta,
+            st.status(),
+            args.color_moved,
+            args.word_diff,
+            args.submodule_log,
+            prefix,
+          );
+        }
+      }
+    }
+  }
+  if hidden > 0 {
+    println!(" ... and {} more", hidden);
+  }
+}
+
+// format the commit time of the oid if the commit can be found in the repo
+fn commit_time_str(repo: &Repository, id: Oid, mode: DateMode) -> String {
+  match repo.find_commit(id) {
+    Ok(c) => format!(" ({})", format_time(c.time(), mode, chrono::Local::now())),
+    Err(_) => String::new(),
+  }
+}
+
+// the path of the repo for display, relative to the base path, a bare repo shows its git dir
+pub fn repo_display_path(repo: &Repository, base_path: &Path) -> String {
+  let repo_dir = repo.workdir().unwrap_or(repo.path());
+  let repo_dir = repo_dir
+    .canonicalize()
+    .unwrap_or_else(|_| repo_dir.to_path_buf());
+  relative_path(&repo_dir, base_path)
+}
+
+// the path of the submodule in the messages about it, relative to the base path like the repos
+pub fn submodule_display_path(parent: &Repository, sub: &Submodule) -> String {
+  match parent.workdir().and_then(|p| p.canonicalize().ok()) {
+    Some(dir) => relative_path(&dir.join(sub.path()), relative_base()),
+    None => display_path(sub.path()),
+  }
+}
+
+// the path relative to the base with ".." where it's outside, both are canonical paths
+// a path on another drive can't be relative and is returned whole
+pub fn relative_path(path: &Path, base: &Path) -> String {
+  let path_parts: Vec<_> = path.components().collect();
+  let base_parts: Vec<_> = base.components().collect();
+  let common = path_parts
+    .iter()
+    .zip(base_parts.iter())
+    .take_while(|(a, b)| a == b)
+    .count();
+  if common == 0 {
+    return display_path(path);
+  }
+  let mut rel = PathBuf::new();
+  for _ in common..base_parts.len() {
+    rel.push("..");
+  }
+  for c in path_parts[common..].iter() {
+    rel.push(c);
+  }
+  if rel.as_os_str().is_empty() {
+    String::from(".")
+  } else {
+    display_path(&rel)
+  }
+}
 
-      print_patch(repo, &delta, st.status());
+// the path with forward slashes and without the verbatim prefix which canonicalize adds on windows
+// `\\?\C:\dir` becomes `C:/dir` and `\\?\UNC\server\share` becomes `//server/share`
+// other verbatim paths, like volume guids, can't be written without the prefix and are kept
+pub fn display_path(path: &Path) -> String {
+  let path_str = path.display().to_string().replace("\\", "/");
+  if let Some(rest) = path_str.strip_prefix("//?/") {
+    let bytes = rest.as_bytes();
+    let is_drive = bytes.len() >= 2
+      && bytes[0].is_ascii_alphabetic()
+      && bytes[1] == b':'
+      && (bytes.len() == 2 || bytes[2] == b'/');
+    if is_drive {
+      return String::from(rest);
     }
+    if let Some(share) = rest
+      .strip_prefix("UNC/")
+      .or_else(|| rest.strip_prefix("unc/"))
+    {
+      return format!("//{}", share);
+    }
+  }
+  path_str
+}
+
+// warn if the origin of the submodule is not the url recorded in .gitmodules
+fn check_url(parent: &Repository, sub: &Submodule, sub_repo: &Repository, work_dir: &Path) {
+  let name = match sub.name() {
+    Some(n) => n,
+    None => return,
+  };
+  let recorded = parent
+    .workdir()
+    .and_then(|p| Config::open(&p.join(".gitmodules")).ok())
+    .and_then(|c| c.get_string(&format!("submodule.{}.url", name)).ok())
+    .map(|u| resolve_url(parent, &u));
+  let origin = sub_repo
+    .find_remote("origin")
+    .ok()
+    .and_then(|r| r.url().map(String::from));
+  if let (Some(recorded), Some(origin)) = (recorded, origin) {
+    if recorded != origin {
+      println!(
+        "{} {}\n .gitmodules: {}\n origin:      {}",
+        "URL mismatch:".yellow(),
+        repo_display_path(sub_repo, work_dir).bright_blue(),
+        recorded,
+        origin
+      );
+    }
+  }
+}
+
+// the short id of the head, or the text for a repo without commits
+fn short_head(head_id: Oid, unborn: &str) -> String {
+  if head_id.is_zero() {
+    String::from(unborn)
+  } else {
+    head_id.to_string()[..7].to_string()
   }
 }
 
-// recursively list change of the repo and it's submodule
-pub fn show_repo_status(repo: &Repository, work_dir: &PathBuf, head: Oid, args: &mut StatusArgs) {
+// how long ago the recorded commit of the submodule was made, if it's older than --stale
+// the root repo has no recorded commit, and a commit not fetched can't tell its age
+fn stale_age(
+  repo: &Repository,
+  recorded: Option<Oid>,
+  limit: Option<chrono::Duration>,
+) -> Option<chrono::Duration> {
+  let commit = repo.find_commit(recorded?).ok()?;
+  let age = chrono::Local::now().timestamp() - commit.committer().when().seconds();
+  let age = chrono::Duration::seconds(age);
+  (age > limit?).then_some(age)
+}
+
+// print the header and the summary of the repo
+// `recorded` is the commit recorded by the parent, None for the root repo
+// `orig` is ORIG_HEAD for --since-orig if HEAD has moved from it
+// `counts` are the staged and working tree changes
+fn print_status_header(
+  repo: &Repository,
+  repo_str: &str,
+  recorded: Option<Oid>,
+  head_id: Oid,
+  orig: Option<Oid>,
+  args: &StatusArgs,
+  counts: (usize, usize),
+) {
+  let head = recorded.unwrap_or(head_id);
+  let (staged, work_tree) = counts;
+  print!(
+    "{} @ {}",
+    format!("Repo: {}", repo_str).bright_blue(),
+    short_head(head_id, "(no commits yet)").green()
+  );
+  if args.ahead_of_recorded && head_id != head {
+    if let Ok((ahead, _)) = repo.graph_ahead_behind(head_id, head) {
+      if ahead > 0 {
+        print!(
+          " {}",
+          format!("(+{} commits beyond recorded)", ahead).yellow()
+        );
+      }
+    }
+  }
+  if repo.state() != RepositoryState::Clean {
+    print!(" | {}", format!("State: {:?}", repo.state()).purple());
+  }
+  if let Some(age) = stale_age(repo, recorded, args.stale) {
+    print!(
+      " | {}",
+      format!("STALE (last bump {})", format_duration(age)).yellow()
+    );
+  }
+  println!();
+
+  if head_id != head {
+    println!(
+      "Repo head changed:\n From {}{}\n To   {}{}",
+      head,
+      commit_time_str(repo, head, args.date),
+      head_id,
+      commit_time_str(repo, head_id, args.date)
+    );
+    if args.submodule_log {
+      print_submodule_log(repo, head, head_id);
+    }
+  }
+  if let Some(orig) = orig {
+    println!("Since ORIG_HEAD {}:", &orig.to_string()[..7]);
+    print_submodule_log(repo, orig, head_id);
+  }
+
+  println!("{} changes staged", staged);
+  println!("{} changes in working tree", work_tree);
+}
+
+// the commits ahead and behind the upstream of the current branch
+fn ahead_behind(repo: &Repository) -> Option<(usize, usize)> {
+  let head = repo.head().ok()?;
+  if !head.is_branch() {
+    return None;
+  }
+  let branch = Branch::wrap(head);
+  let upstream = branch.upstream().ok()?;
+  repo
+    .graph_ahead_behind(branch.get().target()?, upstream.get().target()?)
+    .ok()
+}
+
+// print the status of the repo in a single grep-friendly line
+// the arguments are like the ones of print_status_header
+fn print_status_line(
+  repo: &Repository,
+  repo_str: &str,
+  recorded: Option<Oid>,
+  head_id: Oid,
+  orig: Option<Oid>,
+  args: &StatusArgs,
+  counts: (usize, usize),
+) {
+  let head = recorded.unwrap_or(head_id);
+  let (staged, work_tree) = counts;
+  print!(
+    "{} {} S:{} W:{}",
+    repo_str.bright_blue(),
+    short_head(head_id, "unborn").green(),
+    staged,
+    work_tree
+  );
+  if let Some((ahead, behind)) = ahead_behind(repo) {
+    print!(" ahead:{} behind:{}", ahead, behind);
+  }
+  if head_id != head {
+    print!(" moved-from:{}", &head.to_string()[..7]);
+  }
+  if let Some(orig) = orig {
+    print!(" orig-head:{}", &orig.to_string()[..7]);
+  }
+  if let Some(age) = stale_age(repo, recorded, args.stale) {
+    print!(" stale:{}d", age.num_days());
+  }
+  if repo.state() != RepositoryState::Clean {
+    print!(" {}", format!("state:{:?}", repo.state()).purple());
+  }
+  println!();
+}
+
+// the submodule pointers recorded in the tree, by their path
+fn recorded_submodules(commit: Option<&Commit>) -> HashMap<String, Oid> {
+  let mut links = HashMap::new();
+  if let Some(c) = commit {
+    c.tree()
+      .expect("Get tree failed")
+      .walk(TreeWalkMode::PreOrder, |root, e| {
+        if e.kind() == Some(ObjectType::Commit) {
+          links.insert(format!("{}{}", root, e.name().unwrap_or_default()), e.id());
+        }
+        TreeWalkResult::Ok
+      })
+      .expect("Walk tree failed");
+  }
+  links
+}
+
+// recursively print the submodule pointers changed between the two commits of the repo
+fn compare_recorded(repo: &Repository, old: Option<&Commit>, new: Option<&Commit>, prefix: &str) {
+  let old_links = recorded_submodules(old);
+  let new_links = recorded_submodules(new);
+  let mut paths: Vec<&String> = old_links.keys().chain(new_links.keys()).collect();
+  paths.sort();
+  paths.dedup();
+  for path in paths {
+    let display_path = format!("{}{}", prefix, path);
+    let (old_id, new_id) = (old_links.get(path), new_links.get(path));
+    if old_id == new_id {
+      continue;
+    }
+    let sub_repo = repo.find_submodule(path).and_then(|s| s.open()).ok();
+    match (old_id, new_id) {
+      (Some(o), Some(n)) => {
+        print!(
+          "{}: {}..{}",
+          display_path.bright_blue(),
+          &o.to_string()[..7],
+          &n.to_string()[..7]
+        );
+        match sub_repo
+          .as_ref()
+          .and_then(|r| r.graph_ahead_behind(*n, *o).ok())
+        {
+          Some((ahead, behind)) => println!(" (+{}/-{} commits)", ahead, behind),
+          None => println!(" (commits not found)"),
+        }
+      }
+      (None, Some(n)) => println!(
+        "{}: added at {}",
+        display_path.bright_blue(),
+        &n.to_string()[..7]
+      ),
+      (Some(o), None) => println!(
+        "{}: removed, was {}",
+        display_path.bright_blue(),
+        &o.to_string()[..7]
+      ),
+      (None, None) => {}
+    }
+    if let Some(ref sub_repo) = sub_repo {
+      let find = |id: Option<&Oid>| id.and_then(|id| sub_repo.find_commit(*id).ok());
+      compare_recorded(
+        sub_repo,
+        find(old_id).as_ref(),
+        find(new_id).as_ref(),
+        &format!("{}/", display_path),
+      );
+    }
+  }
+}
+
+// print how the submodule pointers recorded in the root repo changed from the revision to HEAD
+pub fn show_recorded_changes(repo: &Repository, rev: &str) -> Result<(), GitSubError> {
+  let old = repo
+    .revparse_single(rev)
+    .and_then(|o| o.peel_to_commit())
+    .map_err(|e| GitSubError::RevParse(String::from(rev), e))?;
+  let new = repo.head()?.peel_to_commit()?;
+  compare_recorded(repo, Some(&old), Some(&new), "");
+  Ok(())
+}
+
+// print the changed paths of the repo relative to the working path, each once
+// only the paths of the status entries are used, so no patch is built and no blob is read
+fn print_names(
+  repo: &Repository,
+  work_dir: &Path,
+  index_stat_vec: &[StatusEntry],
+  work_tree_stat_vec: &[StatusEntry],
+  separators: &Separators,
+) {
+  let repo_dir = repo
+    .workdir()
+    .and_then(|p| p.canonicalize().ok())
+    .unwrap_or_default();
+  let mut printed = HashSet::new();
+  for path in index_stat_vec
+    .iter()
+    .chain(work_tree_stat_vec.iter())
+    .filter_map(|st| st.path())
+  {
+    if printed.insert(path) {
+      let path = relative_path(&repo_dir.join(path), work_dir);
+      print!("{}{}", path, separators.end());
+    }
+  }
+}
+
+// the quick check of --root-only, true if neither the index differs from HEAD nor the working tree
+// from the index, any failure leaves it to the full status
+fn is_clean_by_diff(repo: &Repository, show: ShowOption, options: &mut DiffOptions) -> bool {
+  let index = match repo.index() {
+    Ok(i) => i,
+    Err(_) => return false,
+  };
+  if show != ShowOption::WorkTree {
+    // an unborn head is the empty tree
+    let tree = repo.head().and_then(|h| h.peel_to_tree()).ok();
+    match repo.diff_tree_to_index(tree.as_ref(), Some(&index), Some(options)) {
+      Ok(d) if d.deltas().len() == 0 => {}
+      _ => return false,
+    }
+  }
+  if show != ShowOption::Index {
+    match repo.diff_index_to_workdir(Some(&index), Some(options)) {
+      Ok(d) if d.deltas().len() == 0 => {}
+      _ => return false,
+    }
+  }
+  true
+}
+
+// recursively list change of the repo and it's submodule, return if any of them is dirty
+// `recorded` is the commit the parent repo records for the submodule in its HEAD, the repo
+// has drifted if its own HEAD is elsewhere; the root repo and a newly added submodule have none
+pub fn show_repo_status(
+  repo: &Repository,
+  work_dir: &PathBuf,
+  recorded: Option<Oid>,
+  args: &mut StatusArgs,
+) -> Result<bool, GitSubError> {
+  // a clean root needs no statuses, the rest of its header is filled in as usual
+  let quick_clean = match args.root_check.take() {
+    Some(mut o) if !args.all => is_clean_by_diff(repo, args.show_option, &mut o),
+    _ => false,
+  };
   let index_statuses = match args.show_option {
-    ShowOption::Both | ShowOption::Index => Some(
-      repo
-        .statuses(Some(args.status_option.show(StatusShow::Index)))
-        .unwrap_or_else(|e| {
-          err_exit!("Get status failed: {}", e);
-        }),
-    ),
+    _ if quick_clean => None,
+    ShowOption::Both | ShowOption::Index => {
+      Some(repo.statuses(Some(args.status_option.show(StatusShow::Index)))?)
+    }
     _ => None,
   };
+  let repo_dir = repo
+    .workdir()
+    .and_then(|p| p.canonicalize().ok())
+    .unwrap_or_default();
+  // the collapsed untracked directory "build/" is under the excluded "build" as well
+  let is_excluded = |st: &StatusEntry| {
+    st.path().is_some_and(|p| {
+      let path = repo_dir.join(p);
+      args.exclude_dirs.iter().any(|d| path.starts_with(d))
+    })
+  };
   let index_stat_vec = if let Some(ref s) = index_statuses {
     s.iter()
-      .filter(|s| args.diff_filter.test(s.status()))
+      .filter(|s| args.diff_filter.test(s.status()) && !is_excluded(s))
       .collect()
   } else {
     Vec::new()
   };
   let work_tree_statuses = match args.show_option {
-    ShowOption::Both | ShowOption::WorkTree => Some(
-      repo
-        .statuses(Some(args.status_option.show(StatusShow::Workdir)))
-        .unwrap_or_else(|e| {
-          err_exit!("Get status failed: {}", e);
-        }),
-    ),
+    _ if quick_clean => None,
+    ShowOption::Both | ShowOption::WorkTree => {
+      Some(repo.statuses(Some(args.status_option.show(StatusShow::Workdir)))?)
+    }
     _ => None,
   };
   let work_tree_stat_vec = if let Some(ref s) = work_tree_statuses {
     s.iter()
-      .filter(|s| args.diff_filter.test(s.status()))
+      .filter(|s| args.diff_filter.test(s.status()) && !is_excluded(s))
       .collect()
   } else {
     Vec::new()
   };
-  let head_id = repo
-    .head()
-    .expect("Extract head failed")
-    .resolve()
-    .expect("Resolve reference failed")
-    .target()
-    .expect("Get oid failed");
-  if args.all
-    || !index_stat_vec.is_empty()
-    || !work_tree_stat_vec.is_empty()
-    || repo.state() != RepositoryState::Clean
-    || head_id != head
-  {
-    // make and print repo header
-    let mut repo_dir = repo
-      .workdir()
-      .unwrap_or_else(|| {
-        err_exit!("Extract path failed");
-      })
-      .canonicalize()
-      .unwrap_or_else(|e| {
-        err_exit!("Get canonicalize path failed: {}", e);
-      });
-    if repo_dir != *work_dir {
-      if let Ok(p) = repo_dir.strip_prefix(work_dir) {
-        repo_dir = Path::new(".").join(p);
-      }
+  // resolved once, the header and the drift check use the same commit
+  // a freshly initialized repo has no commit yet, its head is the zero id
+  let head_id = match repo.head() {
+    Ok(h) => h.resolve()?.target().unwrap_or_else(Oid::zero),
+    Err(e) if e.code() == ErrorCode::UnbornBranch => Oid::zero(),
+    Err(e) => return Err(e.into()),
+  };
+  let head = recorded.unwrap_or(head_id);
+  // the counts of the filtered lists, the header, the line and --totals all show these
+  let (staged, work_tree) = (index_stat_vec.len(), work_tree_stat_vec.len());
+  let mut dirty =
+    staged > 0 || work_tree > 0 || repo.state() != RepositoryState::Clean || head_id != head;
+  // the repos without ORIG_HEAD or still at it have nothing to show for --since-orig
+  let orig = if args.since_orig {
+    repo
+      .revparse_single("ORIG_HEAD")
+      .and_then(|o| o.peel_to_commit())
+      .ok()
+      .map(|c| c.id())
+      .filter(|id| *id != head_id)
+  } else {
+    None
+  };
+  if let Some(totals) = args.totals.as_mut() {
+    totals.repos += 1;
+    totals.dirty_repos += dirty as usize;
+    totals.staged += staged;
+    totals.work_tree += work_tree;
+  }
+  if args.quiet {
+    // only the exit code matters, no need to look further
+    if dirty {
+      return Ok(true);
     }
-    let repo_str = repo_dir.display().to_string().replace("\\", "/");
-    print!(
-      "{} @ {}",
-      format!(
-        "Repo: {}",
-        repo_str.strip_prefix("//?/").unwrap_or(&repo_str)
-      )
-      .bright_blue(),
-      &head_id.to_string()[..7].green()
+  } else if args.name_only {
+    print_names(
+      repo,
+      work_dir,
+      &index_stat_vec,
+      &work_tree_stat_vec,
+      &args.separators,
     );
-    if repo.state() != RepositoryState::Clean {
-      print!(" | {}", format!("State: {:?}", repo.state()).purple());
+  } else if args.all || dirty || orig.is_some() || stale_age(repo, recorded, args.stale).is_some() {
+    // make and print repo header
+    let repo_str = &repo_display_path(repo, work_dir);
+    if args.line {
+      let counts = (staged, work_tree);
+      print_status_line(repo, repo_str, recorded, head_id, orig, args, counts);
+    } else {
+      print_status_header(
+        repo,
+        repo_str,
+        recorded,
+        head_id,
+        orig,
+        args,
+        (staged, work_tree),
+      );
+      if !args.is_short {
+        let prefix = if args.root_paths {
+          let repo_dir = repo
+            .workdir()
+            .and_then(|p| p.canonicalize().ok())
+            .unwrap_or_default();
+          patch_prefix(&repo_dir, work_dir)
+        } else {
+          String::new()
+        };
+        let mut shown = 0;
+        let old_width = match args.name_width {
+          Some(0) => {
+            let entries: Vec<&StatusEntry> = index_stat_vec
+              .iter()
+              .chain(work_tree_stat_vec.iter())
+              .collect();
+            old_path_width(&entries)
+          }
+          Some(n) => n,
+          None => 0,
+        };
+        // print staged changes
+        if args.show_option == ShowOption::Both || args.show_option == ShowOption::Index {
+          show_statuses(
+            &index_stat_vec,
+            repo,
+            args,
+            work_dir,
+            &prefix,
+            &mut shown,
+            old_width,
+          );
+        }
+        // print un-staged changes
+        if args.show_option == ShowOption::Both || args.show_option == ShowOption::WorkTree {
+          show_statuses(
+            &work_tree_stat_vec,
+            repo,
+            args,
+            work_dir,
+            &prefix,
+            &mut shown,
+            old_width,
+          );
+        }
+      }
     }
-    print!("\n");
+  }
 
-    if head_id != head {
-      println!("Repo head changed:\n From {}\n To   {}", head, head_id);
-    }
+  // the block of the repo goes out before the submodules are checked, so the output of a
+  // big tree streams repo by repo even when it's piped
+  std::io::Write::flush(&mut std::io::stdout())?;
 
-    println!("{} changes staged", index_stat_vec.len());
-    println!("{} changes in working tree", work_tree_stat_vec.len());
-    if !args.is_short {
-      // print staged changes
-      if args.show_option == ShowOption::Both || args.show_option == ShowOption::Index {
-        show_statuses(&index_stat_vec, repo, args.show_patch);
+  // recurse submodules, sorted by path so the output is the same across runs and machines
+  let mut subs = repo.submodules()?;
+  subs.sort_by(|a, b| a.path().cmp(b.path()));
+  for sub in subs.iter() {
+    if args.ignore_submodules && sub.ignore_rule() == SubmoduleIgnore::All {
+      continue;
+    }
+    let sub_repo = match sub.open() {
+      Ok(r) => r,
+      Err(e) if is_strict() => {
+        return Err(GitSubError::SubmoduleOpen(
+          submodule_display_path(repo, sub),
+          e,
+        ))
       }
-      // print un-staged changes
-      if args.show_option == ShowOption::Both || args.show_option == ShowOption::WorkTree {
-        show_statuses(&work_tree_stat_vec, repo, args.show_patch);
+      Err(e) => {
+        let path = submodule_display_path(repo, sub);
+        eprintln!("Open submodule {} failed: {}", path, e.message());
+        continue;
       }
+    };
+    if args.check_urls && !args.quiet {
+      check_url(repo, sub, &sub_repo, work_dir);
+    }
+    dirty |= show_repo_status(&sub_repo, work_dir, sub.head_id(), args)?;
+    if args.quiet && dirty {
+      return Ok(true);
     }
   }
+  Ok(dirty)
+}
 
-  // recurse submodules
-  for sub in repo
-    .submodules()
-    .unwrap_or_else(|e| {
-      err_exit!("Get submodules failed: {}", e);
-    })
-    .iter()
-  {
-    show_repo_status(
-      &sub.open().unwrap_or_else(|e| {
-        err_exit!("Open repo failed, not a git repo? {}", e);
-      }),
-      work_dir,
-      sub.head_id().expect("Get submodule head id failed"),
-      args,
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn is_binary_checks_the_first_8000_bytes() {
+    let mut content = vec![b'a'; 10000];
+    assert!(!is_binary(&content));
+    content[7999] = 0;
+    assert!(is_binary(&content));
+    content[7999] = b'a';
+    content[8000] = 0;
+    assert!(!is_binary(&content));
+    assert!(is_binary(b"\0"));
+    assert!(!is_binary(b""));
+  }
+
+  #[test]
+  fn display_path_strips_the_verbatim_prefix() {
+    let display = |s: &str| display_path(Path::new(s));
+    assert_eq!(display(r"\\?\C:\Users\me\repo"), "C:/Users/me/repo");
+    assert_eq!(display(r"\\?\d:\"), "d:/");
+    assert_eq!(display(r"\\?\C:"), "C:");
+    assert_eq!(display(r"\\?\UNC\server\share\repo"), "//server/share/repo");
+    assert_eq!(display(r"\\?\unc\server\share"), "//server/share");
+    // a volume guid has no path without the prefix
+    let guid = r"\\?\Volume{12345678-1234-1234-1234-123456789abc}\repo";
+    assert_eq!(
+      display(guid),
+      "//?/Volume{12345678-1234-1234-1234-123456789abc}/repo"
     );
+    // not a drive letter, e.g. `\\?\CC:\`
+    assert_eq!(display(r"\\?\CC:\x"), "//?/CC:/x");
+    assert_eq!(display(r"C:\Users\me"), "C:/Users/me");
+    assert_eq!(display(r"\\server\share\repo"), "//server/share/repo");
+    assert_eq!(display("/home/me/repo"), "/home/me/repo");
+    assert_eq!(display("sub/a"), "sub/a");
+  }
+
+  #[test]
+  fn relative_path_walks_up_and_down() {
+    let relative = |p: &str, base: &str| relative_path(Path::new(p), Path::new(base));
+    assert_eq!(relative("/repo/sub/a", "/repo"), "sub/a");
+    assert_eq!(relative("/repo", "/repo"), ".");
+    assert_eq!(relative("/repo", "/repo/sub/a"), "../..");
+    assert_eq!(relative("/repo/lib/b", "/repo/sub/a"), "../../lib/b");
+    // nothing in common, like another drive on windows
+    assert_eq!(relative("D:/repo/lib", "C:/repo"), "D:/repo/lib");
   }
 }
@@ -0,0 +1,66 @@
+use super::*;
+use regex::Regex;
+
+// the trailers of a commit message like `Signed-off-by: name <email>`
+// they are the `Key: value` lines of the last paragraph, a line starting with whitespace
+// continues the value of the line before, like `git interpret-trailers`
+pub fn parse_trailers(message: &str) -> Vec<(String, String)> {
+  let paragraphs: Vec<&str> = message
+    .trim_end()
+    .split("\n\n")
+    .filter(|p| !p.trim().is_empty())
+    .collect();
+  // the subject alone has no trailers
+  if paragraphs.len() < 2 {
+    return Vec::new();
+  }
+  let mut trailers: Vec<(String, String)> = Vec::new();
+  for line in paragraphs[paragraphs.len() - 1].lines() {
+    if line.starts_with(char::is_whitespace) {
+      match trailers.last_mut() {
+        Some((_, value)) => {
+          value.push(' ');
+          value.push_str(line.trim());
+          continue;
+        }
+        None => return Vec::new(),
+      }
+    }
+    let (key, value) = match line.split_once(':') {
+      Some(kv) => kv,
+      None => return Vec::new(),
+    };
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+      return Vec::new();
+    }
+    trailers.push((key.to_string(), value.trim().to_string()));
+  }
+  trailers
+}
+
+// the filter of `--grep-trailer <key>[=<regex>]`, the key is matched case-insensitively
+pub struct TrailerFilter {
+  key: String,
+  value: Option<Regex>,
+}
+
+impl TrailerFilter {
+  pub fn from(s: &str) -> TrailerFilter {
+    let (key, value) = match s.split_once('=') {
+      Some((k, v)) => (k, Some(v)),
+      None => (s, None),
+    };
+    TrailerFilter {
+      key: key.trim().to_string(),
+      value: value.map(|v| {
+        Regex::new(v).unwrap_or_else(|_| err_exit!("Crate regex for grep-trailer failed"))
+      }),
+    }
+  }
+
+  pub fn test(&self, trailers: &[(String, String)]) -> bool {
+    trailers.iter().any(|(k, v)| {
+      k.eq_ignore_ascii_case(&self.key) && self.value.as_ref().is_none_or(|r| r.is_match(v))
+    })
+  }
+}
@@ -0,0 +1,55 @@
+use super::*;
+use clap::*;
+
+pub struct TreeArgs {}
+
+impl TreeArgs {
+  pub fn build_arg() -> Command {
+    Command::new("tree").about("Show the hierarchy of submodules with their recorded commits")
+  }
+}
+
+impl From<&clap::ArgMatches> for TreeArgs {
+  fn from(_: &clap::ArgMatches) -> TreeArgs {
+    TreeArgs {}
+  }
+}
+
+// `indent` is the prefix of the lines drawn by the ancestors
+fn print_submodule_tree(repo: &Repository, indent: &str) -> Result<(), GitSubError> {
+  let subs = repo.submodules()?;
+  for (i, sub) in subs.iter().enumerate() {
+    let last = i + 1 == subs.len();
+    print!(
+      "{}{}{}",
+      indent,
+      if last { "└── " } else { "├── " },
+      sub
+        .path()
+        .display()
+        .to_string()
+        .replace("\\", "/")
+        .bright_blue()
+    );
+    match sub.head_id() {
+      Some(id) => print!(" {}", &id.to_string()[..7].green()),
+      None => print!(" {}", "(not recorded)".yellow()),
+    }
+    if let Some(branch) = sub.branch() {
+      print!(" (branch: {})", branch);
+    }
+    println!();
+    if let Ok(sub_repo) = sub.open() {
+      print_submodule_tree(
+        &sub_repo,
+        &format!("{}{}", indent, if last { "    " } else { "│   " }),
+      )?;
+    }
+  }
+  Ok(())
+}
+
+pub fn show_tree(repo: Repository, work_dir: &Path, _args: TreeArgs) -> Result<(), GitSubError> {
+  println!("{}", repo_display_path(&repo, work_dir).bright_blue());
+  print_submodule_tree(&repo, "")
+}
@@ -0,0 +1,181 @@
+use super::*;
+use clap::*;
+
+pub struct VerifyArgs {
+  all_branches: bool,
+  objects: bool,
+  quiet: bool,
+}
+
+impl VerifyArgs {
+  pub fn build_arg() -> Command {
+    Command::new("verify")
+      .about("Check the commits recorded for the submodules are present in them")
+      .arg(
+        Arg::new("all-branches")
+          .long("all-branches")
+          .action(ArgAction::SetTrue)
+          .help("Check the tips of all local branches of the root repo instead of HEAD"),
+      )
+      .arg(
+        Arg::new("objects")
+          .long("objects")
+          .action(ArgAction::SetTrue)
+          .help(
+            "Also check the trees and files of the recorded commits are in the object databases",
+          ),
+      )
+      .arg(
+        Arg::new("quiet")
+          .long("quiet")
+          .short('q')
+          .action(ArgAction::SetTrue)
+          .help("Print nothing, only exit with 1 if any recorded commit is missing"),
+      )
+  }
+}
+
+impl From<&clap::ArgMatches> for VerifyArgs {
+  fn from(matches: &clap::ArgMatches) -> VerifyArgs {
+    VerifyArgs {
+      all_branches: matches.get_flag("all-branches"),
+      objects: matches.get_flag("objects"),
+      quiet: matches.get_flag("quiet"),
+    }
+  }
+}
+
+// walk the submodules recorded in the commit like `collect_submodule_heads_with_rev`,
+// but report the missing commits instead of failing on them
+// return the problems as "<path>: <message>", or "MISSING <path>: <oid> ..." with --objects
+fn verify_commit(
+  repo: &Repository,
+  commit: &Commit,
+  prefix: &str,
+  args: &VerifyArgs,
+  problems: &mut Vec<String>,
+) {
+  commit
+    .tree()
+    .expect("Get tree failed")
+    .walk(TreeWalkMode::PreOrder, |root, e| {
+      if e.kind() != Some(ObjectType::Commit) {
+        return TreeWalkResult::Ok;
+      }
+      let path = format!("{}{}", root, e.name().unwrap_or_default());
+      let display_path = format!("{}{}", prefix, path);
+      let short_id = &e.id().to_string()[..7];
+      let sub = match repo.find_submodule(&path).and_then(|s| s.open()) {
+        Ok(s) => s,
+        Err(_) => {
+          problems.push(format!(
+            "{}: {} can't be checked, the submodule is not initialized",
+            display_path, short_id
+          ));
+          return TreeWalkResult::Ok;
+        }
+      };
+      match sub.find_commit(e.id()) {
+        Ok(sub_commit) => {
+          if args.objects {
+            verify_objects(&sub, &sub_commit, &display_path, problems);
+          }
+          verify_commit(
+            &sub,
+            &sub_commit,
+            &format!("{}/", display_path),
+            args,
+            problems,
+          )
+        }
+        Err(_) if args.objects => problems.push(missing(&display_path, e.id())),
+        Err(_) => problems.push(format!("{}: {} is not fetched", display_path, short_id)),
+      }
+      TreeWalkResult::Ok
+    })
+    .expect("Walk tree failed");
+}
+
+fn missing(path: &str, id: Oid) -> String {
+  format!("MISSING {}: {} (run git submodule update)", path, id)
+}
+
+// check every tree and file of the commit is in the object database of the submodule
+// a partial fetch or a broken clone can have the commit without all of its objects
+fn verify_objects(repo: &Repository, commit: &Commit, path: &str, problems: &mut Vec<String>) {
+  let odb = repo.odb().expect("Get object database failed");
+  let tree_id = commit.tree_id();
+  if !odb.exists(tree_id) {
+    problems.push(missing(path, tree_id));
+    return;
+  }
+  let tree = commit.tree().expect("Get tree failed");
+  tree
+    .walk(TreeWalkMode::PreOrder, |root, e| {
+      // the gitlinks are checked in their own repos
+      if e.kind() == Some(ObjectType::Commit) || odb.exists(e.id()) {
+        return TreeWalkResult::Ok;
+      }
+      let name = e.name().unwrap_or_default();
+      problems.push(missing(&format!("{}/{}{}", path, root, name), e.id()));
+      // the entries of a missing tree can't be read
+      TreeWalkResult::Skip
+    })
+    .expect("Walk tree failed");
+}
+
+fn print_problems(problems: &[String]) {
+  for p in problems.iter() {
+    println!("  {}", p.red());
+  }
+}
+
+// exit with 1 if any recorded commit is missing
+pub fn verify(repo: Repository, args: VerifyArgs) -> Result<(), GitSubError> {
+  let mut failed = false;
+  if args.all_branches {
+    let branches = repo.branches(Some(BranchType::Local))?;
+    for (branch, _) in branches.filter_map(|b| b.ok()) {
+      let name = String::from(branch.name().ok().flatten().unwrap_or("(invalid utf-8)"));
+      let commit = match branch.get().peel_to_commit() {
+        Ok(c) => c,
+        // with --quiet it can't be reported, so it fails the check instead
+        Err(_) if args.quiet => {
+          failed = true;
+          continue;
+        }
+        Err(e) => {
+          warn_or_exit!("Warning: can't resolve branch {}: {}", name, e.message());
+          continue;
+        }
+      };
+      let mut problems = Vec::new();
+      verify_commit(&repo, &commit, "", &args, &mut problems);
+      if args.quiet {
+        failed |= !problems.is_empty();
+      } else if problems.is_empty() {
+        println!("{}: {}", name.bright_blue(), "ok".green());
+      } else {
+        println!("{}: {} missing", name.bright_blue(), problems.len());
+        print_problems(&problems);
+        failed = true;
+      }
+    }
+  } else {
+    let head = repo.head()?.peel_to_commit()?;
+    let mut problems = Vec::new();
+    verify_commit(&repo, &head, "", &args, &mut problems);
+    if args.quiet {
+      failed = !problems.is_empty();
+    } else if problems.is_empty() {
+      println!("All recorded submodule commits are present");
+    } else {
+      print_problems(&problems);
+      failed = true;
+    }
+  }
+  if failed {
+    std::process::exit(1);
+  }
+  Ok(())
+}
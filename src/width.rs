@@ -0,0 +1,69 @@
+// the columns a string takes in the terminal
+// the CJK and emoji characters take two columns, the combining marks and joiners take none
+
+// the ranges of the East Asian wide and fullwidth characters and the emoji
+const WIDE: [(u32, u32); 16] = [
+  (0x1100, 0x115F),
+  (0x2E80, 0x303E),
+  (0x3041, 0x33FF),
+  (0x3400, 0x4DBF),
+  (0x4E00, 0x9FFF),
+  (0xA000, 0xA4CF),
+  (0xAC00, 0xD7A3),
+  (0xF900, 0xFAFF),
+  (0xFE30, 0xFE4F),
+  (0xFF00, 0xFF60),
+  (0xFFE0, 0xFFE6),
+  (0x1F300, 0x1F64F),
+  (0x1F680, 0x1F6FF),
+  (0x1F900, 0x1F9FF),
+  (0x20000, 0x2FFFD),
+  (0x30000, 0x3FFFD),
+];
+
+// combining marks, zero width spaces and joiners, variation selectors
+const ZERO: [(u32, u32); 5] = [
+  (0x0300, 0x036F),
+  (0x200B, 0x200F),
+  (0x20D0, 0x20FF),
+  (0xFE00, 0xFE0F),
+  (0xFE20, 0xFE2F),
+];
+
+fn in_ranges(c: char, ranges: &[(u32, u32)]) -> bool {
+  let c = c as u32;
+  ranges
+    .iter()
+    .any(|(start, end)| (*start..=*end).contains(&c))
+}
+
+pub fn char_width(c: char) -> usize {
+  if c.is_control() || in_ranges(c, &ZERO) {
+    0
+  } else if in_ranges(c, &WIDE) {
+    2
+  } else {
+    1
+  }
+}
+
+pub fn text_width(s: &str) -> usize {
+  s.chars().map(char_width).sum()
+}
+
+// pad the text with spaces to the width, `format!("{:width$}")` counts chars instead of columns
+pub fn pad_to_width(s: &str, width: usize) -> String {
+  format!("{}{}", s, " ".repeat(width.saturating_sub(text_width(s))))
+}
+
+// the longest head of the text which fits the width
+pub fn take_width(s: &str, width: usize) -> &str {
+  let mut used = 0;
+  for (i, c) in s.char_indices() {
+    used += char_width(c);
+    if used > width {
+      return &s[..i];
+    }
+  }
+  s
+}
@@ -0,0 +1,216 @@
+use super::*;
+use clap::*;
+
+// the extensions of source code, --word-diff=auto keeps the line diff for them
+// everything else, e.g. .md, .txt or a file without extension, is taken as prose
+const CODE_EXTENSIONS: [&str; 36] = [
+  "rs", "c", "h", "cc", "cpp", "cxx", "hpp", "cs", "java", "kt", "swift", "go", "py", "rb", "php",
+  "js", "jsx", "ts", "tsx", "lua", "sh", "bash", "bat", "ps1", "sql", "html", "css", "scss",
+  "json", "yaml", "yml", "toml", "xml", "cmake", "gradle", "proto",
+];
+// the code files known by their names
+const CODE_NAMES: [&str; 4] = ["Makefile", "Dockerfile", "CMakeLists.txt", "Cargo.lock"];
+// the largest hunk compared word by word, a bigger one is shown as all removed then all added
+const MAX_WORD_PAIRS: usize = 4_000_000;
+
+// how --word-diff shows the changed words
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum WordDiffMode {
+  // like `[-old-]{+new+}`
+  Plain,
+  // in the colors of the removed and added lines
+  Color,
+  // color for the prose files, the line diff for code
+  Auto,
+}
+
+impl From<&str> for WordDiffMode {
+  fn from(s: &str) -> WordDiffMode {
+    match s {
+      "color" => WordDiffMode::Color,
+      "auto" => WordDiffMode::Auto,
+      _ => WordDiffMode::Plain,
+    }
+  }
+}
+
+// the --word-diff option of the subcommands printing patches
+pub fn word_diff_arg() -> Arg {
+  Arg::new("word-diff")
+    .long("word-diff")
+    .value_name("mode")
+    .num_args(0..=1)
+    .default_missing_value("plain")
+    .value_parser(["plain", "color", "auto"])
+    .conflicts_with("color-moved")
+    .help("Show the changed words instead of lines, auto only does it for prose files like .md and .txt")
+}
+
+pub fn parse_word_diff(matches: &clap::ArgMatches) -> Option<WordDiffMode> {
+  matches
+    .get_one::<String>("word-diff")
+    .map(|s| WordDiffMode::from(s.as_str()))
+}
+
+// check if the file is prose rather than code by its name
+fn is_prose(path: &Path) -> bool {
+  let name = path
+    .file_name()
+    .and_then(|n| n.to_str())
+    .unwrap_or_default();
+  if CODE_NAMES.contains(&name) {
+    return false;
+  }
+  match path.extension().and_then(|e| e.to_str()) {
+    Some(ext) => !CODE_EXTENSIONS.contains(&ext.to_lowercase().as_str()),
+    None => true,
+  }
+}
+
+// the mode the file is shown in, None for the line diff
+pub fn word_diff_for(mode: Option<WordDiffMode>, path: &Path) -> Option<WordDiffMode> {
+  match mode {
+    Some(WordDiffMode::Auto) if is_prose(path) => Some(WordDiffMode::Color),
+    Some(WordDiffMode::Auto) => None,
+    m => m,
+  }
+}
+
+// split the text into the words, the runs of whitespace and the newlines
+fn split_words(text: &str) -> Vec<&str> {
+  let mut tokens = Vec::new();
+  let mut start = 0;
+  let mut space = false;
+  for (i, c) in text.char_indices() {
+    if c == '\n' {
+      if i > start {
+        tokens.push(&text[start..i]);
+      }
+      tokens.push(&text[i..i + 1]);
+      start = i + 1;
+    } else if i > start && c.is_whitespace() != space {
+      tokens.push(&text[start..i]);
+      start = i;
+    }
+    space = c.is_whitespace();
+  }
+  if start < text.len() {
+    tokens.push(&text[start..]);
+  }
+  tokens
+}
+
+// the tokens of the old and new text marked as kept ' ', removed '-' or added '+'
+// by the longest common subsequence
+fn diff_words<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(char, &'a str)> {
+  if old.len() * new.len() > MAX_WORD_PAIRS {
+    let removed = old.iter().map(|t| ('-', *t));
+    return removed.chain(new.iter().map(|t| ('+', *t))).collect();
+  }
+  // lcs[i][j] is the length of the common subsequence of old[i..] and new[j..]
+  let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+  for i in (0..old.len()).rev() {
+    for j in (0..new.len()).rev() {
+      lcs[i][j] = if old[i] == new[j] {
+        lcs[i + 1][j + 1] + 1
+      } else {
+        lcs[i + 1][j].max(lcs[i][j + 1])
+      };
+    }
+  }
+  let (mut i, mut j) = (0, 0);
+  let mut ops = Vec::new();
+  while i < old.len() || j < new.len() {
+    if i < old.len() && j < new.len() && old[i] == new[j] {
+      ops.push((' ', old[i]));
+      i += 1;
+      j += 1;
+    } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+      ops.push(('-', old[i]));
+      i += 1;
+    } else {
+      ops.push(('+', new[j]));
+      j += 1;
+    }
+  }
+  ops
+}
+
+fn print_words(op: char, text: &str, mode: WordDiffMode) {
+  match (op, mode) {
+    ('-', WordDiffMode::Plain) => print!("[-{}-]", text),
+    ('+', WordDiffMode::Plain) => print!("{{+{}+}}", text),
+    ('-', _) => print!("{}", text.paint(color_for("diff.remove"))),
+    ('+', _) => print!("{}", text.paint(color_for("diff.add"))),
+    _ => print!("{}", text),
+  }
+}
+
+// print the removed and added lines as the new text with the changed words marked
+// the line breaks of the new text are kept, so the layout is the new one
+fn print_changed_words(old: &str, new: &str, mode: WordDiffMode) {
+  let (old_words, new_words) = (split_words(old), split_words(new));
+  let ops = diff_words(&old_words, &new_words);
+  // the changed words next to each other are marked as one run
+  let mut run = String::new();
+  let mut run_op = ' ';
+  // a removed line break only ends an output line holding nothing but removed words
+  let (mut has_old, mut has_new) = (false, false);
+  for (op, token) in ops {
+    if op != run_op || token == "\n" {
+      print_words(run_op, &run, mode);
+      run.clear();
+    }
+    run_op = op;
+    if token != "\n" {
+      run.push_str(token);
+      if op == '-' {
+        has_old = true;
+      } else {
+        has_new = true;
+      }
+      continue;
+    }
+    run_op = ' ';
+    if op != '-' || (has_old && !has_new) {
+      println!();
+      has_old = false;
+      has_new = false;
+    }
+  }
+  print_words(run_op, &run, mode);
+}
+
+// print the patch with the changed words, the context lines are shown without the leading space
+pub fn print_patch_words(patch: &mut Patch, mode: WordDiffMode, added: bool, deleted: bool) {
+  let mut lines: Vec<(char, String)> = Vec::new();
+  patch
+    .print(&mut |_, _, line| {
+      let content = String::from_utf8_lossy(line.content()).into_owned();
+      lines.push((line.origin(), content));
+      true
+    })
+    .unwrap();
+  let (mut old, mut new) = (String::new(), String::new());
+  for (origin, content) in lines.iter() {
+    match origin {
+      '-' => old.push_str(content),
+      '+' => new.push_str(content),
+      _ => {
+        if !old.is_empty() || !new.is_empty() {
+          print_changed_words(&old, &new, mode);
+          old.clear();
+          new.clear();
+        }
+        match origin {
+          'F' => print!("{}", file_header(content.as_bytes(), added, deleted)),
+          'H' => print_diff_line('H', content.as_bytes(), false),
+          ' ' | 'B' => print!("{}", content),
+          // the "\ No newline at end of file" markers don't apply to words
+          _ => {}
+        }
+      }
+    }
+  }
+  print_changed_words(&old, &new, mode);
+}
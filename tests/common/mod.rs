@@ -0,0 +1,141 @@
+// the helpers shared by the integration tests
+// the fixtures are built with the git command in the temp directory and git-sub runs on them
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+// the empty home of git and git-sub, so the config of the user doesn't change the output
+fn home_dir() -> PathBuf {
+  let home = std::env::temp_dir().join(format!("git-sub-test-home-{}", std::process::id()));
+  fs::create_dir_all(&home).expect("Create home directory failed");
+  home
+}
+
+// a new empty directory for a fixture, the name is only there to find it when a test fails
+pub fn temp_dir(name: &str) -> PathBuf {
+  let dir = std::env::temp_dir().join(format!(
+    "git-sub-test-{}-{}-{}",
+    std::process::id(),
+    NEXT_DIR.fetch_add(1, Ordering::SeqCst),
+    name
+  ));
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).expect("Create fixture directory failed");
+  dir
+    .canonicalize()
+    .expect("Canonicalize fixture directory failed")
+}
+
+fn isolated(mut cmd: Command, dir: &Path) -> Command {
+  cmd
+    .current_dir(dir)
+    .env("HOME", home_dir())
+    .env("GIT_CONFIG_NOSYSTEM", "1")
+    .env_remove("GIT_DIR")
+    .env_remove("GIT_WORK_TREE")
+    .env_remove("GIT_PAGER")
+    .env_remove("CLICOLOR_FORCE");
+  cmd
+}
+
+// run git in the directory with the dates given in `env` if any, panic if it fails
+pub fn git_with_env(dir: &Path, args: &[&str], env: &[(&str, &str)]) -> String {
+  let mut cmd = isolated(Command::new("git"), dir);
+  cmd
+    .args([
+      "-c",
+      "protocol.file.allow=always",
+      "-c",
+      "init.defaultBranch=master",
+    ])
+    .args([
+      "-c",
+      "user.name=tester",
+      "-c",
+      "user.email=tester@example.com",
+    ])
+    .args(args)
+    .envs(env.iter().copied());
+  let output = cmd.output().expect("Run git failed");
+  assert!(
+    output.status.success(),
+    "git {:?} failed in {}: {}",
+    args,
+    dir.display(),
+    String::from_utf8_lossy(&output.stderr)
+  );
+  String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+pub fn git(dir: &Path, args: &[&str]) -> String {
+  git_with_env(dir, args, &[])
+}
+
+// a new repo with a first commit of the file
+pub fn new_repo(dir: &Path, file: &str, content: &str) -> PathBuf {
+  fs::create_dir_all(dir).expect("Create repo directory failed");
+  git(dir, &["init", "-q"]);
+  commit_file(dir, file, content, &format!("add {}", file));
+  dir.to_path_buf()
+}
+
+pub fn write_file(repo: &Path, file: &str, content: &str) {
+  let path = repo.join(file);
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).expect("Create directory failed");
+  }
+  fs::write(path, content).expect("Write file failed");
+}
+
+// write the file and commit it, return the id of the commit
+pub fn commit_file(repo: &Path, file: &str, content: &str, message: &str) -> String {
+  write_file(repo, file, content);
+  git(repo, &["add", file]);
+  git(repo, &["commit", "-q", "-m", message]);
+  git(repo, &["rev-parse", "HEAD"])
+}
+
+// add the repo as a submodule at the path and commit it, the nested submodules are checked out
+pub fn add_submodule(parent: &Path, sub: &Path, path: &str) {
+  add_submodule_named(parent, sub, path, path);
+}
+
+// like add_submodule, with a name in .gitmodules other than the path
+pub fn add_submodule_named(parent: &Path, sub: &Path, path: &str, name: &str) {
+  let url = sub.to_str().expect("Non utf-8 path");
+  git(
+    parent,
+    &["submodule", "add", "-q", "--name", name, url, path],
+  );
+  git(
+    parent,
+    &["submodule", "update", "-q", "--init", "--recursive"],
+  );
+  git(
+    parent,
+    &["commit", "-q", "-m", &format!("add submodule {}", path)],
+  );
+}
+
+// run git-sub in the directory
+pub fn git_sub(dir: &Path, args: &[&str]) -> Output {
+  let mut cmd = isolated(Command::new(env!("CARGO_BIN_EXE_git-sub")), dir);
+  cmd.args(args).output().expect("Run git-sub failed")
+}
+
+// the stdout of git-sub, panic if it failed
+pub fn git_sub_ok(dir: &Path, args: &[&str]) -> String {
+  let output = git_sub(dir, args);
+  assert!(
+    output.status.success(),
+    "git-sub {:?} failed: {}",
+    args,
+    String::from_utf8_lossy(&output.stderr)
+  );
+  String::from_utf8_lossy(&output.stdout).into_owned()
+}
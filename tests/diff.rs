@@ -0,0 +1,35 @@
+mod common;
+
+use common::*;
+use std::process::Command;
+
+#[test]
+fn no_index_with_absolute_paths_matches_git() {
+  let dir = temp_dir("diff-no-index");
+  write_file(&dir, "old/f.txt", "1\n2\n");
+  write_file(&dir, "new/f.txt", "1\n3\n");
+  write_file(&dir, "new/added.txt", "new\n");
+  write_file(&dir, "old/deleted.txt", "old\n");
+  let (old, new) = (dir.join("old"), dir.join("new"));
+  let (old, new) = (old.to_str().unwrap(), new.to_str().unwrap());
+
+  let output = git_sub(&dir, &["diff", "--no-index", old, new]);
+  let expected = Command::new("git")
+    .current_dir(&dir)
+    .args([
+      "-c",
+      "core.abbrev=7",
+      "diff",
+      "--no-index",
+      "--no-color",
+      old,
+      new,
+    ])
+    .output()
+    .expect("Run git failed");
+  assert_eq!(
+    String::from_utf8_lossy(&output.stdout),
+    String::from_utf8_lossy(&expected.stdout)
+  );
+  assert!(!String::from_utf8_lossy(&output.stdout).contains("a//"));
+}
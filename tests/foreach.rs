@@ -0,0 +1,38 @@
+mod common;
+
+use common::*;
+
+#[test]
+fn arguments_are_run_and_shown_as_separate_words() {
+  let dir = temp_dir("foreach-words");
+  let a = new_repo(&dir.join("a"), "a.txt", "a\n");
+  let root = new_repo(&dir.join("root"), "r.txt", "r\n");
+  add_submodule(&root, &a, "sub/a");
+
+  let words = [
+    "sh",
+    "-c",
+    "printf '%s|' \"$@\" > args.txt",
+    "sh",
+    "a b",
+    "it's",
+  ];
+  let mut args = vec!["foreach", "--dry-run", "--"];
+  args.extend_from_slice(&words);
+  let output = git_sub_ok(&root, &args);
+  assert_eq!(
+    output,
+    "sub/a: sh -c 'printf '\\''%s|'\\'' \"$@\" > args.txt' sh 'a b' 'it'\\''s'\n"
+  );
+  assert!(!root.join("sub/a/args.txt").exists());
+
+  args.remove(1);
+  git_sub_ok(&root, &args);
+  let written = std::fs::read_to_string(root.join("sub/a/args.txt")).unwrap();
+  assert_eq!(written, "a b|it's|");
+
+  // a single word is still a shell command with the variables set
+  git_sub_ok(&root, &["foreach", "echo $name > name.txt"]);
+  let name = std::fs::read_to_string(root.join("sub/a/name.txt")).unwrap();
+  assert_eq!(name, "sub/a\n");
+}
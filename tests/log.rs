@@ -0,0 +1,74 @@
+mod common;
+
+use common::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+// master and feature fork after the first commits, each moves on with its own commits
+fn fork_branches(repo: &Path, name: &str) {
+  commit_file(repo, "shared.txt", "shared\n", &format!("{} shared", name));
+  git(repo, &["checkout", "-q", "-b", "feature"]);
+  commit_file(repo, "f.txt", "f1\n", &format!("{} feature 1", name));
+  commit_file(repo, "f.txt", "f2\n", &format!("{} feature 2", name));
+  git(repo, &["checkout", "-q", "master"]);
+  commit_file(repo, "m.txt", "m\n", &format!("{} master", name));
+}
+
+// how many times each `<repo> <id>` is listed by the porcelain log
+fn listed_commits(output: &str) -> HashMap<String, usize> {
+  let mut counts = HashMap::new();
+  let mut id = "";
+  for line in output.lines() {
+    if let Some(i) = line.strip_prefix("commit ") {
+      id = i;
+    } else if let Some(repo) = line.strip_prefix("repo ") {
+      *counts.entry(format!("{} {}", repo, id)).or_default() += 1;
+    }
+  }
+  counts
+}
+
+#[test]
+fn all_lists_each_commit_of_the_branches_once() {
+  let dir = temp_dir("log-all");
+  let a = new_repo(&dir.join("a"), "a.txt", "a\n");
+  let root = new_repo(&dir.join("root"), "r.txt", "r\n");
+  add_submodule(&root, &a, "sub/a");
+  let sub = root.join("sub/a");
+  git(&sub, &["checkout", "-q", "-B", "master"]);
+  fork_branches(&root, "root");
+  fork_branches(&sub, "a");
+
+  let output = git_sub_ok(&root, &["log", "--all", "--format", "porcelain"]);
+  let listed = listed_commits(&output);
+  let mut expected = 0;
+  for (repo, path) in [(&root, "."), (&sub, "sub/a")] {
+    let ids = git(repo, &["rev-list", "--branches"]);
+    for id in ids.lines() {
+      let key = format!("{} {}", path, id);
+      assert_eq!(listed.get(&key), Some(&1), "{} in:\n{}", key, output);
+    }
+    // the shared history and the commits of both branches
+    assert!(ids.lines().count() >= 4);
+    expected += ids.lines().count();
+  }
+  assert_eq!(listed.len(), expected, "{:?}", listed);
+}
+
+#[test]
+fn repos_outside_the_base_are_shown_relative_to_it() {
+  let dir = temp_dir("log-outside-base");
+  let a = new_repo(&dir.join("a"), "a.txt", "a\n");
+  let root = new_repo(&dir.join("root"), "r.txt", "r\n");
+  add_submodule(&root, &a, "sub/a");
+  let other = dir.join("other");
+  std::fs::create_dir_all(&other).unwrap();
+  let other = other.to_str().unwrap();
+
+  let output = git_sub_ok(&root, &["--relative-base", other, "log"]);
+  let sub_line = output.lines().find(|l| l.contains(" - add a.txt")).unwrap();
+  assert!(sub_line.ends_with("(../root/sub/a)"), "{}", output);
+  let output = git_sub_ok(&root, &["--relative-base", other, "log", "--full"]);
+  assert!(output.contains(" - ../root/sub/a\n"), "{}", output);
+  assert!(output.contains(" - ../root\n"), "{}", output);
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use common::*;
+use std::path::{Path, PathBuf};
+
+// root -> sub/a -> libs/b, the grandchild is named unlike its path in the .gitmodules of sub/a
+fn nested_fixture() -> PathBuf {
+  let dir = temp_dir("ls-files-nested");
+  let b = new_repo(&dir.join("b"), "b.txt", "b\n");
+  let a = new_repo(&dir.join("a"), "a.txt", "a\n");
+  add_submodule_named(&a, &b, "libs/b", "lib-b");
+  let root = new_repo(&dir.join("root"), "r.txt", "r\n");
+  add_submodule(&root, &a, "sub/a");
+  root
+}
+
+// the paths of the `<oid> <path>` lines
+fn listed_paths(output: &str) -> Vec<String> {
+  output
+    .lines()
+    .map(|l| {
+      l.split_once(' ')
+        .expect("Not an `<oid> <path>` line")
+        .1
+        .to_string()
+    })
+    .collect()
+}
+
+fn blob_id(repo: &Path, rev_path: &str) -> String {
+  git(repo, &["rev-parse", rev_path])
+}
+
+#[test]
+fn rev_lists_the_grandchild_submodule() {
+  let root = nested_fixture();
+  let expected = [
+    ".gitmodules",
+    "r.txt",
+    "sub/a/.gitmodules",
+    "sub/a/a.txt",
+    "sub/a/libs/b/b.txt",
+  ];
+  for args in [
+    &["ls-files"][..],
+    &["ls-files", "--rev", "HEAD"],
+    &["ls-files", "--staged"],
+  ] {
+    let output = git_sub_ok(&root, args);
+    assert_eq!(listed_paths(&output), expected, "{:?}", args);
+  }
+  // the object ids come from the repo each file is in
+  let output = git_sub_ok(&root, &["ls-files", "--rev", "HEAD"]);
+  let b_line = output.lines().last().unwrap();
+  let b_id = blob_id(&root.join("sub/a/libs/b"), "HEAD:b.txt");
+  assert_eq!(b_line, format!("{} sub/a/libs/b/b.txt", b_id));
+}
+
+#[test]
+fn rev_uses_the_pointers_recorded_at_each_level() {
+  let root = nested_fixture();
+  let b = root.join("sub/a/libs/b");
+  // the grandchild moves on and only sub/a records it, the root still records the old sub/a
+  commit_file(&b, "b.txt", "b2\n", "change b");
+  let a = root.join("sub/a");
+  git(&a, &["commit", "-q", "-am", "bump b"]);
+  let old_id = blob_id(&b, "HEAD~1:b.txt");
+  let new_id = blob_id(&b, "HEAD:b.txt");
+
+  let output = git_sub_ok(&root, &["ls-files", "--rev", "HEAD"]);
+  assert!(output.contains(&format!("{} sub/a/libs/b/b.txt", old_id)));
+
+  git(&root, &["commit", "-q", "-am", "bump a"]);
+  let output = git_sub_ok(&root, &["ls-files", "--rev", "HEAD"]);
+  assert!(output.contains(&format!("{} sub/a/libs/b/b.txt", new_id)));
+  let output = git_sub_ok(&root, &["ls-files", "--rev", "HEAD~1"]);
+  assert!(output.contains(&format!("{} sub/a/libs/b/b.txt", old_id)));
+}
+
+// the git date of the time the days before now
+fn days_ago(days: u64) -> String {
+  let now = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .unwrap()
+    .as_secs();
+  format!("@{} +0000", now - days * 24 * 3600)
+}
+
+// commit all the changes of the repo, dated the days before now
+fn commit_at(repo: &Path, message: &str, days: u64) {
+  let date = days_ago(days);
+  let env = [
+    ("GIT_AUTHOR_DATE", date.as_str()),
+    ("GIT_COMMITTER_DATE", date.as_str()),
+  ];
+  git_with_env(repo, &["add", "-A"], &env);
+  git_with_env(repo, &["commit", "-q", "-m", message], &env);
+}
+
+#[test]
+fn rev_at_a_reflog_date_uses_the_pointers_of_the_root_at_that_date() {
+  let dir = temp_dir("ls-files-reflog-date");
+  let a = dir.join("a");
+  std::fs::create_dir_all(&a).unwrap();
+  git(&a, &["init", "-q"]);
+  write_file(&a, "a.txt", "v1\n");
+  commit_at(&a, "add a.txt", 10);
+  let root = dir.join("root");
+  std::fs::create_dir_all(&root).unwrap();
+  git(&root, &["init", "-q"]);
+  write_file(&root, "r.txt", "r\n");
+  commit_at(&root, "add r.txt", 10);
+  git(
+    &root,
+    &["submodule", "add", "-q", a.to_str().unwrap(), "sub/a"],
+  );
+  commit_at(&root, "add sub/a", 10);
+
+  // the submodule moves on 8 days ago, but the root records it only 1 day ago
+  let sub = root.join("sub/a");
+  write_file(&sub, "a.txt", "v2\n");
+  commit_at(&sub, "change a.txt", 8);
+  commit_at(&root, "bump sub/a", 1);
+  let v1 = git(&sub, &["rev-parse", "HEAD~1:a.txt"]);
+  let v2 = git(&sub, &["rev-parse", "HEAD:a.txt"]);
+
+  let at = |rev: &str| git_sub_ok(&root, &["ls-files", "--rev", rev]);
+  // 3 days ago the submodule's own HEAD was already at v2, the root still recorded v1
+  let output = at("HEAD@{3 days ago}");
+  assert!(
+    output.contains(&format!("{} sub/a/a.txt", v1)),
+    "{}",
+    output
+  );
+  assert_eq!(at("HEAD@{3 days ago}"), at("HEAD~1"));
+  let output = at("HEAD@{12 hours ago}");
+  assert!(
+    output.contains(&format!("{} sub/a/a.txt", v2)),
+    "{}",
+    output
+  );
+  assert_eq!(at("master@{3 days ago}"), at("HEAD~1"));
+}
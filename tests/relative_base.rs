@@ -0,0 +1,81 @@
+mod common;
+
+use common::*;
+use std::path::PathBuf;
+
+// root (r.txt, d/b.txt) -> sub/a (a.txt), all the files changed in the working tree
+fn changed_fixture(name: &str) -> PathBuf {
+  let dir = temp_dir(name);
+  let a = new_repo(&dir.join("a"), "a.txt", "a\n");
+  let root = new_repo(&dir.join("root"), "r.txt", "r\n");
+  commit_file(&root, "d/b.txt", "b\n", "add b.txt");
+  add_submodule(&root, &a, "sub/a");
+  write_file(&root, "r.txt", "changed\n");
+  write_file(&root, "d/b.txt", "changed\n");
+  write_file(&root.join("sub/a"), "a.txt", "changed\n");
+  root
+}
+
+fn sorted_lines(output: &str) -> Vec<&str> {
+  let mut lines: Vec<&str> = output.lines().collect();
+  lines.sort();
+  lines
+}
+
+#[test]
+fn paths_are_relative_to_the_working_path() {
+  let root = changed_fixture("relative-working-path");
+  let d = root.join("d");
+
+  let output = git_sub_ok(&d, &["status"]);
+  for line in [
+    "Repo: .. @ ",
+    "  M b.txt\n",
+    "  M ../r.txt\n",
+    "Repo: ../sub/a @ ",
+    "  M ../sub/a/a.txt\n",
+  ] {
+    assert!(output.contains(line), "{:?} in:\n{}", line, output);
+  }
+  let output = git_sub_ok(&d, &["status", "--name-only"]);
+  assert_eq!(
+    sorted_lines(&output),
+    ["../r.txt", "../sub/a/a.txt", "b.txt"]
+  );
+
+  let output = git_sub_ok(&d, &["log"]);
+  let sub_line = output.lines().find(|l| l.contains(" - add a.txt")).unwrap();
+  assert!(sub_line.ends_with("(../sub/a)"), "{}", output);
+  let root_line = output.lines().find(|l| l.contains(" - add b.txt")).unwrap();
+  assert!(root_line.ends_with("(..)"), "{}", output);
+
+  let output = git_sub_ok(&d, &["ls-files"]);
+  assert!(output.lines().any(|l| l.ends_with(" b.txt")), "{}", output);
+  assert!(
+    output.lines().any(|l| l.ends_with(" ../sub/a/a.txt")),
+    "{}",
+    output
+  );
+
+  let output = git_sub_ok(&d, &["foreach", "--", "echo $displaypath"]);
+  assert_eq!(output, "Entering '../sub/a'\n../sub/a\n");
+}
+
+#[test]
+fn relative_base_replaces_the_working_path() {
+  let root = changed_fixture("relative-base");
+  let sub = root.join("sub/a");
+  let base = sub.to_str().unwrap();
+
+  let output = git_sub_ok(&root, &["--relative-base", base, "status", "--name-only"]);
+  assert_eq!(
+    sorted_lines(&output),
+    ["../../d/b.txt", "../../r.txt", "a.txt"]
+  );
+  let output = git_sub_ok(&root, &["--relative-base", base, "count-objects"]);
+  let repos: Vec<&str> = output
+    .lines()
+    .filter_map(|l| l.split_whitespace().next())
+    .collect();
+  assert_eq!(repos, ["Repo", "../..", ".", "Total"]);
+}
@@ -0,0 +1,310 @@
+mod common;
+
+use common::*;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+// root -> sub/a, both clean
+fn fixture(name: &str) -> PathBuf {
+  let dir = temp_dir(name);
+  let a = new_repo(&dir.join("a"), "a.txt", "a\n");
+  let root = new_repo(&dir.join("root"), "r.txt", "r\n");
+  add_submodule(&root, &a, "sub/a");
+  root
+}
+
+fn assert_silent(output: &Output, code: i32, args: &[&str]) {
+  assert_eq!(output.status.code(), Some(code), "{:?}", args);
+  assert!(output.stdout.is_empty(), "{:?} printed to stdout", args);
+  assert!(output.stderr.is_empty(), "{:?} printed to stderr", args);
+}
+
+fn run_silent(dir: &Path, args: &[&str], code: i32) {
+  assert_silent(&git_sub(dir, args), code, args);
+}
+
+#[test]
+fn quiet_prints_nothing_and_tells_by_the_exit_code() {
+  let root = fixture("status-quiet");
+  for args in [
+    &["status", "-q"][..],
+    &["status", "--quiet", "--exit-code"],
+    &["verify", "-q"],
+  ] {
+    run_silent(&root, args, 0);
+  }
+
+  write_file(&root.join("sub/a"), "a.txt", "changed\n");
+  run_silent(&root, &["status", "-q"], 1);
+  run_silent(&root, &["status", "--quiet", "--exit-code"], 1);
+  // the root is found clean by the quick check, the submodule still makes it dirty
+  run_silent(&root, &["status", "-q", "--root-only"], 1);
+}
+
+#[test]
+fn verify_quiet_fails_silently_on_a_missing_submodule() {
+  let root = fixture("verify-quiet");
+  let clone = root.parent().unwrap().join("clone");
+  git(
+    root.parent().unwrap(),
+    &["clone", "-q", root.to_str().unwrap(), "clone"],
+  );
+  // the submodule of the clone is not initialized, so its recorded commit can't be found
+  run_silent(&clone, &["verify", "-q"], 1);
+  run_silent(&clone, &["verify", "-q", "--all-branches"], 1);
+}
+
+#[test]
+fn name_only_reads_no_blob() {
+  let root = fixture("status-name-only");
+  let blob = git(&root, &["rev-parse", "HEAD:r.txt"]);
+  write_file(&root, "r.txt", "staged\n");
+  git(&root, &["add", "r.txt"]);
+  write_file(&root, "r.txt", "changed\n");
+  write_file(&root.join("sub/a"), "a.txt", "changed\n");
+  // the HEAD side of both the staged and the working tree change is gone
+  std::fs::remove_file(root.join(".git/objects").join(&blob[..2]).join(&blob[2..]))
+    .expect("Remove blob failed");
+
+  let output = git_sub_ok(&root, &["status", "--name-only"]);
+  let mut names: Vec<&str> = output.lines().collect();
+  names.sort();
+  assert_eq!(names, ["r.txt", "sub/a/a.txt"]);
+}
+
+// the output of the root repo, before the first line about the submodule
+fn root_block(output: &str) -> String {
+  let mut block = String::new();
+  for line in output.split_inclusive('\n') {
+    if line.starts_with("Repo: sub/a") || line.starts_with("sub/a ") {
+      break;
+    }
+    block.push_str(line);
+  }
+  block
+}
+
+// the output and exit code of the status with the options, with and without --root-only
+fn assert_root_only_unchanged(root: &Path, options: &[&str]) {
+  let mut args = vec!["status"];
+  args.extend_from_slice(options);
+  let full = git_sub(root, &args);
+  args.push("--root-only");
+  let root_only = git_sub(root, &args);
+  let full_stdout = String::from_utf8_lossy(&full.stdout);
+  assert!(full_stdout.contains("sub/a"), "{:?}", options);
+  assert_eq!(
+    String::from_utf8_lossy(&root_only.stdout),
+    full_stdout,
+    "{:?}",
+    options
+  );
+  assert_eq!(root_only.status.code(), full.status.code(), "{:?}", options);
+}
+
+#[test]
+fn root_only_prints_the_same_as_the_full_status() {
+  let root = fixture("status-root-only");
+  let sub = root.join("sub/a");
+  write_file(&sub, "a.txt", "changed\n");
+  write_file(&sub, "new.txt", "new\n");
+  git(&sub, &["add", "new.txt"]);
+  let options = [
+    &[][..],
+    &["-p"],
+    &["--stat"],
+    &["--line"],
+    &["--short"],
+    &["--staged"],
+    &["--work-tree"],
+    &["--all", "--exit-code"],
+  ];
+  // a clean root is skipped by the quick check, the dirty submodule is still shown
+  for o in options {
+    assert_root_only_unchanged(&root, o);
+  }
+  // an untracked file alone makes the root dirty as well
+  write_file(&root, "untracked.txt", "untracked\n");
+  for o in options {
+    assert_root_only_unchanged(&root, o);
+  }
+  write_file(&root, "r.txt", "changed\n");
+  write_file(&root, "staged.txt", "staged\n");
+  git(&root, &["add", "staged.txt"]);
+  for o in options {
+    assert_root_only_unchanged(&root, o);
+  }
+}
+
+// a repo with a file to modify, a file to delete and a file left alone
+fn patch_fixture(name: &str) -> PathBuf {
+  let repo = new_repo(&temp_dir(name).join("repo"), "keep.txt", "keep\n");
+  commit_file(&repo, "mod.txt", "1\n2\n3\n", "add mod.txt");
+  commit_file(&repo, "del.txt", "gone\n", "add del.txt");
+  repo
+}
+
+// check the patch applies to a clone of HEAD and gives the files of the working tree
+fn assert_applies(repo: &Path, patch: &str) {
+  assert!(patch.contains("diff --git"), "no patch in:\n{}", patch);
+  let dir = repo.parent().unwrap();
+  git(dir, &["clone", "-q", repo.to_str().unwrap(), "clone"]);
+  let clone = dir.join("clone");
+  let patch_file = dir.join("status.patch");
+  std::fs::write(&patch_file, patch).unwrap();
+  let patch_path = patch_file.to_str().unwrap();
+  git(&clone, &["apply", "--check", patch_path]);
+  git(&clone, &["apply", patch_path]);
+  for file in ["keep.txt", "mod.txt", "new.txt"] {
+    assert_eq!(
+      std::fs::read_to_string(clone.join(file)).unwrap(),
+      std::fs::read_to_string(repo.join(file)).unwrap(),
+      "{}",
+      file
+    );
+  }
+  assert!(!clone.join("del.txt").exists());
+}
+
+#[test]
+fn staged_patch_applies_with_git_apply() {
+  let repo = patch_fixture("status-patch-staged");
+  // the new file in the index is the reversed blob to buffer patch
+  write_file(&repo, "new.txt", "new\nfile\n");
+  write_file(&repo, "mod.txt", "1\ntwo\n3\n");
+  git(&repo, &["add", "new.txt", "mod.txt"]);
+  git(&repo, &["rm", "-q", "del.txt"]);
+
+  let patch = git_sub_ok(&repo, &["status", "-p", "--staged"]);
+  assert!(patch.contains("new file mode 100644\nindex 0000000.."));
+  assert!(patch.contains("--- /dev/null\n+++ b/new.txt\n"));
+  assert!(patch.contains("deleted file mode 100644\n"));
+  assert!(patch.contains("--- a/del.txt\n+++ /dev/null\n"));
+  assert_applies(&repo, &patch);
+}
+
+#[test]
+fn work_tree_patch_applies_with_git_apply() {
+  let repo = patch_fixture("status-patch-work-tree");
+  write_file(&repo, "new.txt", "new\nfile\n");
+  write_file(&repo, "mod.txt", "1\ntwo\n3\n");
+  std::fs::remove_file(repo.join("del.txt")).unwrap();
+
+  let patch = git_sub_ok(&repo, &["status", "-p", "--work-tree"]);
+  assert!(patch.contains("--- /dev/null\n+++ b/new.txt\n"));
+  assert!(patch.contains("--- a/del.txt\n+++ /dev/null\n"));
+  assert_applies(&repo, &patch);
+}
+
+#[test]
+fn moved_submodule_head_is_reported_for_the_submodule_only() {
+  let root = fixture("status-head-moved");
+  let sub = root.join("sub/a");
+  let recorded = git(&sub, &["rev-parse", "HEAD"]);
+  // the submodule moves past the commit recorded by the root, the root isn't committed
+  let moved = commit_file(&sub, "a.txt", "moved\n", "move a");
+
+  for args in [&["status"][..], &["status", "--all"]] {
+    let output = git_sub_ok(&root, args);
+    let root_part = root_block(&output);
+    let sub_part = &output[root_part.len()..];
+    assert!(
+      !root_part.contains("Repo head changed"),
+      "{:?}:\n{}",
+      args,
+      output
+    );
+    assert!(
+      sub_part.starts_with("Repo: sub/a"),
+      "{:?}:\n{}",
+      args,
+      output
+    );
+    let change = format!("Repo head changed:\n From {} ", recorded);
+    assert!(sub_part.contains(&change), "{:?}:\n{}", args, output);
+    assert!(
+      sub_part.contains(&format!(" To   {} ", moved)),
+      "{:?}:\n{}",
+      args,
+      output
+    );
+  }
+
+  let output = git_sub_ok(&root, &["status", "--line", "--all"]);
+  let moved_from = format!("moved-from:{}", &recorded[..7]);
+  for line in output.lines() {
+    assert_eq!(
+      line.starts_with("sub/a "),
+      line.contains(&moved_from),
+      "{}",
+      output
+    );
+  }
+}
+
+// the number before the suffix of the line, e.g. `3` of `3 changes staged`
+fn count_before(line: &str, suffix: &str) -> Option<usize> {
+  line.strip_suffix(suffix).and_then(|n| n.parse().ok())
+}
+
+#[test]
+fn totals_add_up_the_counts_of_the_repo_headers() {
+  let root = fixture("status-totals");
+  let sub = root.join("sub/a");
+  commit_file(&root, "del.txt", "del\n", "add del.txt");
+  write_file(&root, "staged.txt", "staged\n");
+  write_file(&root, "r.txt", "staged change\n");
+  git(&root, &["add", "staged.txt", "r.txt"]);
+  write_file(&root, "r.txt", "work tree change\n");
+  std::fs::remove_file(root.join("del.txt")).unwrap();
+  write_file(&root, "untracked.txt", "untracked\n");
+  write_file(&sub, "new.txt", "new\n");
+  git(&sub, &["add", "new.txt"]);
+  write_file(&sub, "a.txt", "changed\n");
+
+  // no head moves, so a repo is dirty exactly when its header counts a change
+  for show in [&[][..], &["--staged"], &["--work-tree"]] {
+    for filter in [
+      None,
+      Some("A"),
+      Some("M"),
+      Some("D"),
+      Some("AMd"),
+      Some("AD"),
+    ] {
+      let mut args = vec!["status", "--all", "--totals"];
+      args.extend_from_slice(show);
+      if let Some(f) = filter {
+        args.extend_from_slice(&["--diff-filter", f]);
+      }
+      let output = git_sub_ok(&root, &args);
+      let (mut staged, mut work_tree, mut repos, mut dirty) = (0, 0, 0, 0);
+      let mut repo_counts = None;
+      for line in output.lines() {
+        if line.starts_with("Repo: ") {
+          repos += 1;
+        } else if let Some(n) = count_before(line, " changes staged") {
+          staged += n;
+          repo_counts = Some(n);
+        } else if let Some(n) = count_before(line, " changes in working tree") {
+          work_tree += n;
+          dirty += (n + repo_counts.take().unwrap() > 0) as usize;
+        }
+      }
+      assert_eq!(repos, 2, "{:?}:\n{}", args, output);
+      let total = format!(
+        "Total: {} changes staged, {} changes in working tree, {} of {} repos dirty",
+        staged, work_tree, dirty, repos
+      );
+      assert_eq!(output.lines().last(), Some(total.as_str()), "{:?}", args);
+      if show == ["--staged"] {
+        assert_eq!(work_tree, 0, "{:?}", args);
+      } else if show == ["--work-tree"] {
+        assert_eq!(staged, 0, "{:?}", args);
+      }
+      if filter.is_none() {
+        assert!(staged + work_tree > 0, "{:?}", args);
+      }
+    }
+  }
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use common::*;
+use std::path::PathBuf;
+
+// a clone of root -> sub/a without `submodule update`, so sub/a can't be opened
+fn uninitialized_clone() -> PathBuf {
+  let dir = temp_dir("strict");
+  let a = new_repo(&dir.join("a"), "a.txt", "a\n");
+  let root = new_repo(&dir.join("root"), "r.txt", "r\n");
+  add_submodule(&root, &a, "sub/a");
+  git(&dir, &["clone", "-q", root.to_str().unwrap(), "clone"]);
+  dir.join("clone")
+}
+
+#[test]
+fn uninitialized_submodule_is_skipped_unless_strict() {
+  let clone = uninitialized_clone();
+  for args in [
+    &["log"][..],
+    &["log", "--revision", "HEAD"],
+    &["status"],
+    &["diff"],
+    &["foreach", "--", "true"],
+  ] {
+    let output = git_sub(&clone, args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "{:?}: {}", args, stderr);
+    assert!(stderr.contains("Open submodule "), "{:?}: {}", args, stderr);
+    assert!(stderr.contains("sub/a failed: "), "{:?}: {}", args, stderr);
+
+    let mut strict_args = vec!["--strict"];
+    strict_args.extend_from_slice(args);
+    let output = git_sub(&clone, &strict_args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+      output.status.code(),
+      Some(1),
+      "{:?}: {}",
+      strict_args,
+      stderr
+    );
+    assert!(
+      stderr.contains("sub/a failed: "),
+      "{:?}: {}",
+      strict_args,
+      stderr
+    );
+  }
+  // the root is still shown without --strict
+  let output = git_sub_ok(&clone, &["log"]);
+  assert!(output.contains("add submodule sub/a"), "{}", output);
+}